@@ -1,9 +1,13 @@
-pub const SYSCALLS_NRO: usize = 333;
+pub const SYSCALLS_NRO: usize = 470;
+
+/// Row of a number which is not a syscall
+const UNUSED: [&str; 7] = [""; 7];
+
 /// Sys call table as shown at https://chromium.googlesource.com/chromiumos/docs/+/master/constants/syscalls.md#x86_64-64_bit
 /// and https://github.com/torvalds/linux/blob/master/arch/x86/entry/syscalls/syscall_64.tbl
 /// The row is formatted like:
 /// | syscall_name | rdi | rsi | rdx | r10 | r8 | r9 |
-pub const TABLE: [[&str; 7]; SYSCALLS_NRO] = [
+pub static TABLE: [[&str; 7]; SYSCALLS_NRO] = [
     [
         "read",
         "unsigned int fd",
@@ -872,12 +876,12 @@ pub const TABLE: [[&str; 7]; SYSCALLS_NRO] = [
     ["vhangup", "", "", "", "", "", ""],
     [
         "modify_ldt",
-        "SYS_modify_ldt",
         "int func",
         "void ptr[.bytecount]",
         "unsigned long bytecount",
         "",
         "",
+        "",
     ],
     [
         "pivot_root",
@@ -900,12 +904,12 @@ pub const TABLE: [[&str; 7]; SYSCALLS_NRO] = [
     ],
     [
         "arch_prctl",
-        "SYS_arch_prctl",
         "int code",
         "unsigned long addr",
-        " ",
-        " ",
-        " ",
+        "",
+        "",
+        "",
+        "",
     ],
     [
         "adjtimex",
@@ -2113,19 +2117,541 @@ pub const TABLE: [[&str; 7]; SYSCALLS_NRO] = [
         "struct statx *buffer",
         "",
     ],
+    [
+        "io_pgetevents",
+        "aio_context_t ctx_id",
+        "long min_nr",
+        "long nr",
+        "struct io_event *events",
+        "struct __kernel_timespec *timeout",
+        "const struct __aio_sigset *usig",
+    ],
+    [
+        "rseq",
+        "struct rseq *rseq",
+        "u32 rseq_len",
+        "int flags",
+        "u32 sig",
+        "",
+        "",
+    ],
+    ["uretprobe", "", "", "", "", "", ""],
+    // 336 to 423 are not used on x86_64, to keep the numbers of the other architectures
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    UNUSED,
+    [
+        "pidfd_send_signal",
+        "int pidfd",
+        "int sig",
+        "siginfo_t *info",
+        "unsigned int flags",
+        "",
+        "",
+    ],
+    [
+        "io_uring_setup",
+        "u32 entries",
+        "struct io_uring_params *p",
+        "",
+        "",
+        "",
+        "",
+    ],
+    [
+        "io_uring_enter",
+        "unsigned int fd",
+        "u32 to_submit",
+        "u32 min_complete",
+        "u32 flags",
+        "const void *argp",
+        "size_t argsz",
+    ],
+    [
+        "io_uring_register",
+        "unsigned int fd",
+        "unsigned int opcode",
+        "void *arg",
+        "unsigned int nr_args",
+        "",
+        "",
+    ],
+    [
+        "open_tree",
+        "int dfd",
+        "const char *filename",
+        "unsigned flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "move_mount",
+        "int from_dfd",
+        "const char *from_pathname",
+        "int to_dfd",
+        "const char *to_pathname",
+        "unsigned int flags",
+        "",
+    ],
+    [
+        "fsopen",
+        "const char *fs_name",
+        "unsigned int flags",
+        "",
+        "",
+        "",
+        "",
+    ],
+    [
+        "fsconfig",
+        "int fd",
+        "unsigned int cmd",
+        "const char *key",
+        "const void *value",
+        "int aux",
+        "",
+    ],
+    [
+        "fsmount",
+        "int fs_fd",
+        "unsigned int flags",
+        "unsigned int attr_flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "fspick",
+        "int dfd",
+        "const char *path",
+        "unsigned int flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "pidfd_open",
+        "pid_t pid",
+        "unsigned int flags",
+        "",
+        "",
+        "",
+        "",
+    ],
+    [
+        "clone3",
+        "struct clone_args *uargs",
+        "size_t size",
+        "",
+        "",
+        "",
+        "",
+    ],
+    [
+        "close_range",
+        "unsigned int fd",
+        "unsigned int max_fd",
+        "unsigned int flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "openat2",
+        "int dfd",
+        "const char *filename",
+        "struct open_how *how",
+        "size_t usize",
+        "",
+        "",
+    ],
+    [
+        "pidfd_getfd",
+        "int pidfd",
+        "int fd",
+        "unsigned int flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "faccessat2",
+        "int dfd",
+        "const char *filename",
+        "int mode",
+        "int flags",
+        "",
+        "",
+    ],
+    [
+        "process_madvise",
+        "int pidfd",
+        "const struct iovec *vec",
+        "size_t vlen",
+        "int behavior",
+        "unsigned int flags",
+        "",
+    ],
+    [
+        "epoll_pwait2",
+        "int epfd",
+        "struct epoll_event *events",
+        "int maxevents",
+        "const struct __kernel_timespec *timeout",
+        "const sigset_t *sigmask",
+        "size_t sigsetsize",
+    ],
+    [
+        "mount_setattr",
+        "int dfd",
+        "const char *path",
+        "unsigned int flags",
+        "struct mount_attr *uattr",
+        "size_t usize",
+        "",
+    ],
+    [
+        "quotactl_fd",
+        "unsigned int fd",
+        "unsigned int cmd",
+        "qid_t id",
+        "void *addr",
+        "",
+        "",
+    ],
+    [
+        "landlock_create_ruleset",
+        "const struct landlock_ruleset_attr *attr",
+        "size_t size",
+        "__u32 flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "landlock_add_rule",
+        "int ruleset_fd",
+        "enum landlock_rule_type rule_type",
+        "const void *rule_attr",
+        "__u32 flags",
+        "",
+        "",
+    ],
+    [
+        "landlock_restrict_self",
+        "int ruleset_fd",
+        "__u32 flags",
+        "",
+        "",
+        "",
+        "",
+    ],
+    ["memfd_secret", "unsigned int flags", "", "", "", "", ""],
+    [
+        "process_mrelease",
+        "int pidfd",
+        "unsigned int flags",
+        "",
+        "",
+        "",
+        "",
+    ],
+    [
+        "futex_waitv",
+        "struct futex_waitv *waiters",
+        "unsigned int nr_futexes",
+        "unsigned int flags",
+        "struct __kernel_timespec *timeout",
+        "clockid_t clockid",
+        "",
+    ],
+    [
+        "set_mempolicy_home_node",
+        "unsigned long start",
+        "unsigned long len",
+        "unsigned long home_node",
+        "unsigned long flags",
+        "",
+        "",
+    ],
+    [
+        "cachestat",
+        "unsigned int fd",
+        "struct cachestat_range *cstat_range",
+        "struct cachestat *cstat",
+        "unsigned int flags",
+        "",
+        "",
+    ],
+    [
+        "fchmodat2",
+        "int dfd",
+        "const char *filename",
+        "umode_t mode",
+        "unsigned int flags",
+        "",
+        "",
+    ],
+    [
+        "map_shadow_stack",
+        "unsigned long addr",
+        "unsigned long size",
+        "unsigned int flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "futex_wake",
+        "void *uaddr",
+        "unsigned long mask",
+        "int nr",
+        "unsigned int flags",
+        "",
+        "",
+    ],
+    [
+        "futex_wait",
+        "void *uaddr",
+        "unsigned long val",
+        "unsigned long mask",
+        "unsigned int flags",
+        "struct __kernel_timespec *timeout",
+        "clockid_t clockid",
+    ],
+    [
+        "futex_requeue",
+        "struct futex_waitv *waiters",
+        "unsigned int flags",
+        "int nr_wake",
+        "int nr_requeue",
+        "",
+        "",
+    ],
+    [
+        "statmount",
+        "const struct mnt_id_req *req",
+        "struct statmount *buf",
+        "size_t bufsize",
+        "unsigned int flags",
+        "",
+        "",
+    ],
+    [
+        "listmount",
+        "const struct mnt_id_req *req",
+        "u64 *mnt_ids",
+        "size_t nr_mnt_ids",
+        "unsigned int flags",
+        "",
+        "",
+    ],
+    [
+        "lsm_get_self_attr",
+        "unsigned int attr",
+        "struct lsm_ctx *ctx",
+        "u32 *size",
+        "u32 flags",
+        "",
+        "",
+    ],
+    [
+        "lsm_set_self_attr",
+        "unsigned int attr",
+        "struct lsm_ctx *ctx",
+        "u32 size",
+        "u32 flags",
+        "",
+        "",
+    ],
+    [
+        "lsm_list_modules",
+        "u64 *ids",
+        "u32 *size",
+        "u32 flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "mseal",
+        "unsigned long start",
+        "size_t len",
+        "unsigned long flags",
+        "",
+        "",
+        "",
+    ],
+    [
+        "setxattrat",
+        "int dfd",
+        "const char *pathname",
+        "unsigned int at_flags",
+        "const char *name",
+        "const struct xattr_args *args",
+        "size_t size",
+    ],
+    [
+        "getxattrat",
+        "int dfd",
+        "const char *pathname",
+        "unsigned int at_flags",
+        "const char *name",
+        "struct xattr_args *args",
+        "size_t size",
+    ],
+    [
+        "listxattrat",
+        "int dfd",
+        "const char *pathname",
+        "unsigned int at_flags",
+        "char *list",
+        "size_t size",
+        "",
+    ],
+    [
+        "removexattrat",
+        "int dfd",
+        "const char *pathname",
+        "unsigned int at_flags",
+        "const char *name",
+        "",
+        "",
+    ],
+    [
+        "open_tree_attr",
+        "int dfd",
+        "const char *filename",
+        "unsigned int flags",
+        "struct mount_attr *uattr",
+        "size_t usize",
+        "",
+    ],
+    [
+        "file_getattr",
+        "int dfd",
+        "const char *filename",
+        "struct file_attr *ufattr",
+        "size_t usize",
+        "unsigned int at_flags",
+        "",
+    ],
+    [
+        "file_setattr",
+        "int dfd",
+        "const char *filename",
+        "struct file_attr *ufattr",
+        "size_t usize",
+        "unsigned int at_flags",
+        "",
+    ],
 ];
 
 /// Returns the name for a `rax` register
 pub fn syscall_name(rax: u64) -> &'static str {
-    if (rax as usize) > SYSCALLS_NRO {
+    if (rax as usize) >= SYSCALLS_NRO {
         return "";
     }
 
     TABLE[rax as usize][0]
 }
 
+/// Return the number of a syscall from its `name`, if it exists in the table
+pub fn syscall_number(name: &str) -> Option<u64> {
+    TABLE
+        .iter()
+        .position(|row| !row[0].is_empty() && row[0] == name)
+        .map(|nro| nro as u64)
+}
+
 pub fn rdi(rax: u64) -> &'static str {
-    if (rax as usize) > SYSCALLS_NRO {
+    if (rax as usize) >= SYSCALLS_NRO {
         return "";
     }
 
@@ -2133,7 +2659,7 @@ pub fn rdi(rax: u64) -> &'static str {
 }
 
 pub fn rsi(rax: u64) -> &'static str {
-    if (rax as usize) > SYSCALLS_NRO {
+    if (rax as usize) >= SYSCALLS_NRO {
         return "";
     }
 
@@ -2141,7 +2667,7 @@ pub fn rsi(rax: u64) -> &'static str {
 }
 
 pub fn rdx(rax: u64) -> &'static str {
-    if (rax as usize) > SYSCALLS_NRO {
+    if (rax as usize) >= SYSCALLS_NRO {
         return "";
     }
 
@@ -2149,7 +2675,7 @@ pub fn rdx(rax: u64) -> &'static str {
 }
 
 pub fn r10(rax: u64) -> &'static str {
-    if (rax as usize) > SYSCALLS_NRO {
+    if (rax as usize) >= SYSCALLS_NRO {
         return "";
     }
 
@@ -2157,7 +2683,7 @@ pub fn r10(rax: u64) -> &'static str {
 }
 
 pub fn r8(rax: u64) -> &'static str {
-    if (rax as usize) > SYSCALLS_NRO {
+    if (rax as usize) >= SYSCALLS_NRO {
         return "";
     }
 
@@ -2165,7 +2691,7 @@ pub fn r8(rax: u64) -> &'static str {
 }
 
 pub fn r9(rax: u64) -> &'static str {
-    if (rax as usize) > SYSCALLS_NRO {
+    if (rax as usize) >= SYSCALLS_NRO {
         return "";
     }
 
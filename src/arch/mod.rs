@@ -5,3 +5,8 @@ pub mod linux;
 pub fn syscall_name(rax: u64) -> String {
     rax.to_string()
 }
+
+/// Generic `syscall_number` called by a not-defined table
+pub fn syscall_number(name: &str) -> Option<u64> {
+    name.parse::<u64>().ok()
+}
@@ -0,0 +1,100 @@
+use crate::{
+    event::Value,
+    registers::RegistersData,
+    trace::{ProcessEvent, ProcessEventKind},
+};
+use nix::unistd::Pid;
+use serde_json::{json, Map};
+use std::time::Duration;
+
+/// Start of a trace in the Trace Event format, before the events
+pub const HEADER: &str = "{\"displayTimeUnit\":\"ns\",\"traceEvents\":[";
+
+/// Returns a time from a monotonic clock in microseconds, the unit of the Trace Event format
+fn microseconds(time: Duration) -> f64 {
+    time.as_nanos() as f64 / 1e3
+}
+
+/// Returns a complete (`X`) event of a syscall, taken at its exit, on the track of its thread,
+/// followed by a comma
+pub fn syscall(reg: &RegistersData) -> anyhow::Result<String> {
+    let event = reg.event(false);
+
+    let mut args = Map::new();
+    for argument in event.args {
+        let value = match argument.value {
+            Value::Signed(value) => json!(value),
+            Value::Unsigned(value) => json!(value),
+            Value::String(value) => json!(value),
+        };
+        args.insert(argument.name, value);
+    }
+    if !event.unfinished {
+        args.insert("ret".to_string(), json!(event.retval));
+    }
+    if let Some(errno) = event.errno {
+        args.insert("errno".to_string(), json!(errno));
+    }
+    if event.injected {
+        args.insert("injected".to_string(), json!(true));
+    }
+    if let Some(violation) = event.violation {
+        args.insert("violation".to_string(), json!(violation));
+    }
+
+    let name = if event.name.is_empty() {
+        event.number.to_string()
+    } else {
+        event.name
+    };
+
+    Ok(format!(
+        "{},",
+        json!({
+            "name": name,
+            "cat": "syscall",
+            "ph": "X",
+            "ts": microseconds(reg.entry()),
+            "dur": microseconds(reg.duration()),
+            "pid": event.pid,
+            "tid": event.tid,
+            "args": args,
+        })
+    ))
+}
+
+/// Returns an instant (`i`) event of a signal, on the track of its thread, or of the end of a
+/// process, on the track of the process, followed by a comma
+pub fn process(event: &ProcessEvent) -> anyhow::Result<String> {
+    let (name, scope) = match event.kind {
+        ProcessEventKind::Signal(signal) => (signal.to_string(), "t"),
+        ProcessEventKind::Exited(code) => (format!("exited with {code}"), "p"),
+        ProcessEventKind::Killed(signal) => (format!("killed by {signal}"), "p"),
+    };
+
+    Ok(format!(
+        "{},",
+        json!({
+            "name": name,
+            "cat": "process",
+            "ph": "i",
+            "s": scope,
+            "ts": microseconds(event.time),
+            "pid": event.process.as_raw(),
+            "tid": event.pid.as_raw(),
+        })
+    ))
+}
+
+/// Returns the end of a trace in the Trace Event format, naming the track of the process `pid`
+pub fn footer(pid: Pid, name: &str) -> String {
+    format!(
+        "{}]}}",
+        json!({
+            "name": "process_name",
+            "ph": "M",
+            "pid": pid.as_raw(),
+            "args": { "name": name },
+        })
+    )
+}
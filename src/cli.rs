@@ -1,8 +1,38 @@
-use clap::Parser;
+use crate::{
+    event::OutputFormat,
+    filter::{parse_errno, parse_path, Filter},
+    inject::{Delay, Injection},
+    output::parse_size,
+    policy::{Policy, PolicyAction},
+    predicate::Predicate,
+    registers::{ColorChoice, TimestampFormat},
+    stats::SortBy,
+};
+use clap::{Parser, Subcommand};
+use nix::errno::Errno;
+
+/// Tools run instead of tracing a process
+#[derive(Subcommand)]
+pub enum Tool {
+    /// Compare two traces recorded with `--record` or written with `--format jsonl`
+    ///
+    /// The syscalls are aligned ignoring the addresses, the file descriptors and the
+    /// timestamps, and the filters select the syscalls to compare. Exits with 1 if the traces
+    /// differ
+    Diff {
+        /// Trace used as reference
+        a: String,
+        /// Trace compared to the reference
+        b: String,
+    },
+}
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub tool: Option<Tool>,
+
     /// Command to execute from ptrace
     #[arg(short, long)]
     pub command: Option<String>,
@@ -12,14 +42,159 @@ pub struct Args {
     #[arg(short = 'p', long)]
     pub attach: Option<i32>,
 
-    /// Show only defined sys calls. Multi values separated by comma `,`
+    /// Show only defined sys calls. Multi values separated by comma `,`: a value can be a name
+    /// (`openat`) or a number (`257`), a class (`%file`, `%network`, `%process`, `%memory`, `%signal`, `%ipc`,
+    /// `%desc`) or a regex (`/^stat/`), and it can be negated with `!`
     #[arg(short = 'f', long)]
-    pub filter: Option<String>,
+    pub filter: Option<Filter>,
+
+    /// Show only syscalls which returned an error
+    #[arg(short = 'Z', long = "failed-only", conflicts_with = "successful_only")]
+    pub failed_only: bool,
+
+    /// Show only syscalls which returned without an error
+    #[arg(short = 'z', long = "successful-only")]
+    pub successful_only: bool,
+
+    /// Show only syscalls which failed with one of these errors. Multi values separated by
+    /// comma `,`, eg: `ENOENT,EACCES`
+    #[arg(long, value_delimiter = ',', value_parser = parse_errno)]
+    pub errno: Vec<Errno>,
+
+    /// Show only syscalls which touch this path, as argument or through a file descriptor
+    /// opened on it. It can be repeated
+    #[arg(short = 'P', long, value_parser = parse_path)]
+    pub path: Vec<String>,
+
+    /// Show only syscalls which use these file descriptors as argument. Multi values separated
+    /// by comma `,`
+    #[arg(long, value_delimiter = ',')]
+    pub fd: Vec<i32>,
+
+    /// Show only syscalls which satisfy a predicate over arguments, return value and duration,
+    /// like `write:fd==2`, `mmap:length>1MiB`, `openat:filename~/etc/*` or `duration>10ms`.
+    /// Comparisons can be joined with `&&`. It can be repeated to show syscalls which satisfy any
+    /// of them
+    #[arg(short = 'w', long = "where")]
+    pub predicates: Vec<Predicate>,
+
+    /// Make syscalls fail skipping them or tamper their values, like `openat:error=ENOENT:when=3+`,
+    /// `getuid:retval=0` or `write:arg2=1`. `when` can be `N` (only the Nth call), `N+` (from
+    /// the Nth call) or `N+step` (every `step` calls from the Nth) and `probability` can be set
+    /// like `probability=0.5` or `probability=50%`. It can be repeated, and the first rule which
+    /// fires on a syscall is applied
+    #[arg(long)]
+    pub inject: Vec<Injection>,
+
+    /// Sleep before resuming the process at the entry or at the exit of syscalls, like
+    /// `read:exit=200ms` or `connect:enter=1s`. It can be repeated
+    #[arg(long)]
+    pub delay: Vec<Delay>,
+
+    /// Enforce the policy defined in a file: every line is a rule like `allow read,write,%memory`
+    /// for the allowed syscalls or `path /usr/lib` for the allowed path prefixes
+    #[arg(long, value_parser = Policy::load)]
+    pub policy: Option<Policy>,
+
+    /// What to do with the syscalls outside the policy
+    #[arg(long = "policy-action", value_enum, default_value_t = PolicyAction::Deny)]
+    pub policy_action: PolicyAction,
+
+    /// Print a summary with count, errors and time for every syscall at the exit
+    #[arg(short = 'C', long)]
+    pub summary: bool,
+
+    /// Print only the summary, without the output of every syscall. It hides the TUI
+    #[arg(long = "summary-only")]
+    pub summary_only: bool,
 
-    /// Write the output to a file instead of the standard output
+    /// Key used to sort the summary
+    #[arg(long = "summary-sort", value_enum, default_value_t = SortBy::Time)]
+    pub summary_sort: SortBy,
+
+    /// Print the 50th, 90th, 99th percentile and the max latency for every syscall at the exit
+    #[arg(long)]
+    pub latency: bool,
+
+    /// Like `--latency` but split by file descriptor
+    #[arg(long = "latency-by-fd")]
+    pub latency_by_fd: bool,
+
+    /// Format of the timestamp shown before every syscall [default: rfc3339, or none with
+    /// `--format strace`]
+    #[arg(short = 't', long, value_enum)]
+    pub timestamps: Option<TimestampFormat>,
+
+    /// Show the timestamps in UTC instead of the local timezone
+    #[arg(long, default_value_t = false)]
+    pub utc: bool,
+
+    /// Show the stack of the process at the entry of every syscall beneath it, like
+    /// `strace -k`, with the source lines when there is debug info, also in a separate file of
+    /// `/usr/lib/debug`. In the TUI, `s` shows the stack of the syscall at the top of the screen
+    /// and `h` its latency histogram, which doesn't need `--stack`
+    #[arg(short = 'k', long, default_value_t = false, conflicts_with = "load")]
+    pub stack: bool,
+
+    /// Format of the syscalls written to the standard output and to the file. With `jsonl`,
+    /// `chrome-trace` and `csv` the summary and the latency are written to the standard error
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// When to color the output. Files are colored only with `always`
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Record every syscall and process event in a compact binary file, to analyse it later
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<String>,
+
+    /// Load a trace recorded with `--record` or written with `--format jsonl` instead of
+    /// tracing a process
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "command",
+            "attach",
+            "record",
+            "inject",
+            "delay",
+            "policy",
+            "policy_action"
+        ]
+    )]
+    pub load: Option<String>,
+
+    /// Write the output to a file instead of the standard output. A `FILE` like `|command` is
+    /// the same as `--pipe-to command`
     #[arg(long = "file")]
     pub file_to_print: Option<String>,
 
+    /// Send the output to the standard input of a command run with `sh -c`, eg: `jq .`. When
+    /// the command is too slow to read it, the tracee waits for it up to 100ms, then the lines
+    /// are dropped until it catches up
+    #[arg(long, value_name = "COMMAND", conflicts_with = "file_to_print")]
+    pub pipe_to: Option<String>,
+
+    /// Rotate the file when it reaches this size, eg: `100M`. The rotated files are `FILE.1`,
+    /// the newest, to `FILE.N`. Not available with `chrome-trace`, which is a single document
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, requires = "file_to_print")]
+    pub max_size: Option<u64>,
+
+    /// Number of rotated files kept with `--max-size`, the oldest ones are removed
+    #[arg(long, value_name = "N", default_value_t = 5, requires = "max_size")]
+    pub max_files: usize,
+
+    /// Compress the rotated files with gzip, as `FILE.N.gz`
+    #[arg(long, default_value_t = false, requires = "max_size")]
+    pub compress: bool,
+
+    /// Write the syscalls of every process to `FILE.<pid>`, like `strace -ff`, instead of
+    /// interleaving them in the file. The summary and the latency are still written to `FILE`
+    #[arg(long, default_value_t = false, requires = "file_to_print")]
+    pub per_process: bool,
+
     /// If defined, it hides the TUI
     #[arg(long = "no-tui", default_value_t = false)]
     pub no_tui: bool,
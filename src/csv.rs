@@ -0,0 +1,58 @@
+use crate::{event::Value, registers::RegistersData};
+
+/// Header of the CSV output, the columns of every row
+pub const HEADER: &str =
+    "timestamp,pid,tid,syscall,arg0,arg1,arg2,arg3,arg4,arg5,retval,errno,duration";
+
+/// Quote a field if it contains a comma, a quote or a line break, doubling the quotes
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Returns a row of a syscall, taken at its exit, with the process of the thread in `pid`, the
+/// decoded arguments in the columns `arg0`..`arg5` and the duration in seconds. The timestamp is
+/// in UTC if `utc`
+pub fn row(reg: &RegistersData, utc: bool) -> String {
+    let event = reg.event(utc);
+
+    let mut args: Vec<String> = event
+        .args
+        .into_iter()
+        .map(|argument| match argument.value {
+            Value::Signed(value) => value.to_string(),
+            Value::Unsigned(value) => value.to_string(),
+            Value::String(value) => value,
+        })
+        .collect();
+    args.resize(6, String::new());
+
+    let name = if event.name.is_empty() {
+        event.number.to_string()
+    } else {
+        event.name
+    };
+
+    let mut fields = vec![
+        event.timestamp,
+        event.pid.to_string(),
+        event.tid.to_string(),
+        name,
+    ];
+    fields.extend(args);
+    fields.push(match event.unfinished {
+        true => "?".to_string(),
+        false => event.retval.to_string(),
+    });
+    fields.push(event.errno.unwrap_or_default());
+    fields.push(format!("{:.9}", reg.duration().as_secs_f64()));
+
+    fields
+        .iter()
+        .map(|value| field(value))
+        .collect::<Vec<String>>()
+        .join(",")
+}
@@ -0,0 +1,452 @@
+use crate::{
+    cli::Args,
+    fds::{is_fd, AT_FDCWD},
+    registers::{argument_name, is_string, RegistersData},
+    replay::Replay,
+    trace::Syscalls,
+};
+use std::fmt::Write;
+
+/// Syscalls which return a file descriptor
+const FD_RETURNS: [&str; 14] = [
+    "open",
+    "openat",
+    "openat2",
+    "creat",
+    "dup",
+    "dup2",
+    "dup3",
+    "socket",
+    "accept",
+    "accept4",
+    "eventfd2",
+    "epoll_create1",
+    "memfd_create",
+    "pidfd_open",
+];
+
+/// Syscalls which return an address
+const ADDRESS_RETURNS: [&str; 4] = ["mmap", "brk", "mremap", "shmat"];
+
+/// Syscalls which return a process or a thread ID
+const PID_RETURNS: [&str; 10] = [
+    "getpid",
+    "getppid",
+    "gettid",
+    "clone",
+    "clone3",
+    "fork",
+    "vfork",
+    "set_tid_address",
+    "wait4",
+    "waitid",
+];
+
+/// Maximum number of syscalls added or removed to align two traces, as the memory used by the
+/// alignment grows with its square
+const MAX_EDITS: usize = 2048;
+
+/// Syscall of a trace without the values which change between runs of the same program
+#[derive(Debug)]
+struct Normalized {
+    /// Index of the syscall in its trace, counting from 1
+    index: usize,
+    /// Syscall with its normalized arguments, eg: `openat(AT_FDCWD, "/etc/passwd", 524288, 0)`,
+    /// compared to align the traces
+    call: String,
+    /// Normalized return value, eg: `fd`, `0` or `ENOENT`
+    ret: String,
+}
+
+impl Normalized {
+    /// Normalize a syscall replacing the addresses, the file descriptors and the process IDs by
+    /// placeholders, and ignoring the output buffers
+    fn new(index: usize, reg: &RegistersData) -> Self {
+        let args: Vec<String> = reg
+            .args()
+            .into_iter()
+            .map(|(argument, raw)| {
+                let name = argument_name(argument);
+                if is_fd(argument) && raw as i32 == AT_FDCWD {
+                    "AT_FDCWD".to_string()
+                } else if is_fd(argument) {
+                    "fd".to_string()
+                } else if is_string(argument) && name != "buf" {
+                    format!("{:?}", reg.string(raw))
+                } else if argument.contains('*')
+                    || argument.contains('[')
+                    || ["addr", "start", "brk"].contains(&name)
+                {
+                    "addr".to_string()
+                } else if ["pid", "tid", "upid"].contains(&name) {
+                    "pid".to_string()
+                } else {
+                    raw.to_string()
+                }
+            })
+            .collect();
+
+        let name = if reg.name().is_empty() {
+            reg.number().to_string()
+        } else {
+            reg.name().to_string()
+        };
+
+        let ret = match reg.errno() {
+            _ if reg.unfinished() => "?".to_string(),
+            Some(errno) => format!("{errno:?}"),
+            None if FD_RETURNS.contains(&reg.name()) => "fd".to_string(),
+            None if ADDRESS_RETURNS.contains(&reg.name()) => "addr".to_string(),
+            None if PID_RETURNS.contains(&reg.name()) && reg.retval() > 0 => "pid".to_string(),
+            None => reg.retval().to_string(),
+        };
+
+        Self {
+            index,
+            call: format!("{name}({})", args.join(", ")),
+            ret,
+        }
+    }
+}
+
+impl std::fmt::Display for Normalized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{} {} = {}", self.index, self.call, self.ret)
+    }
+}
+
+/// Step of the alignment of two traces
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    /// Syscalls at (index in a, index in b) with the same normalized call
+    Same(usize, usize),
+    /// Syscall only in a
+    Removed(usize),
+    /// Syscall only in b
+    Added(usize),
+}
+
+/// Align two sequences of syscalls by their calls with the Myers diff algorithm, which is fast
+/// when the traces are similar. Returns `None` if they need more than `MAX_EDITS` syscalls added
+/// or removed
+fn align(a: &[Normalized], b: &[Normalized]) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // Furthest x of the diagonals `-d - 1..=d + 1` before every step `d`, to walk back the path
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    'search: for d in 0..=max as isize {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize].call == b[y as usize].call {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+
+        if d as usize == MAX_EDITS {
+            return None;
+        }
+    }
+
+    // Walk back from the end following the path found at every `d`
+    let mut edits = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().skip(1).rev() {
+        let d = d as isize;
+        let furthest = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && furthest(k - 1) < furthest(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = furthest(previous_k);
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Same(x as usize, y as usize));
+        }
+
+        if x == previous_x {
+            y -= 1;
+            edits.push(Edit::Added(y as usize));
+        } else {
+            x -= 1;
+            edits.push(Edit::Removed(x as usize));
+        }
+    }
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        edits.push(Edit::Same(x as usize, y as usize));
+    }
+
+    edits.reverse();
+    Some(edits)
+}
+
+/// Returns the first syscall where the traces differ by their call or their return value,
+/// comparing them in order without aligning them
+fn divergence(a: &[Normalized], b: &[Normalized]) -> Option<Edit> {
+    let same = a
+        .iter()
+        .zip(b)
+        .take_while(|(a, b)| a.call == b.call && a.ret == b.ret)
+        .count();
+
+    match (a.get(same), b.get(same)) {
+        (Some(_), Some(_)) => Some(Edit::Same(same, same)),
+        (Some(_), None) => Some(Edit::Removed(same)),
+        (None, Some(_)) => Some(Edit::Added(same)),
+        (None, None) => None,
+    }
+}
+
+/// Load the syscalls of a trace selected by the filters of `args`
+fn load(path: &str, args: &Args) -> anyhow::Result<Vec<Normalized>> {
+    let mut replay = Replay::open(path, args)?;
+
+    let mut syscalls = vec![];
+    while let Some(reg) = replay.next()? {
+        syscalls.push(Normalized::new(syscalls.len() + 1, &reg));
+    }
+
+    Ok(syscalls)
+}
+
+/// Returns the start of the report of the differences of the traces at `a` and `b`, with their
+/// `first` divergence
+fn report(
+    (a, left): (&str, &[Normalized]),
+    (b, right): (&str, &[Normalized]),
+    first: Edit,
+) -> Result<String, std::fmt::Error> {
+    let mut report = String::new();
+    writeln!(report, "--- {a} ({} syscalls)", left.len())?;
+    writeln!(report, "+++ {b} ({} syscalls)", right.len())?;
+
+    writeln!(report, "\nFirst divergence:")?;
+    match first {
+        Edit::Same(x, y) => {
+            writeln!(report, "- {}", left[x])?;
+            writeln!(report, "+ {}", right[y])?;
+        }
+        Edit::Removed(x) => writeln!(report, "- {}", left[x])?,
+        Edit::Added(y) => writeln!(report, "+ {}", right[y])?,
+    }
+
+    Ok(report)
+}
+
+/// Compare the traces at `a` and `b`, recorded with `--record` or written with
+/// `--format jsonl`, returning a report of their differences or `None` if they are equivalent
+pub fn diff(a: &str, b: &str, args: &Args) -> anyhow::Result<Option<String>> {
+    let (left, right) = (load(a, args)?, load(b, args)?);
+    let Some(edits) = align(&left, &right) else {
+        let Some(first) = divergence(&left, &right) else {
+            return Ok(None);
+        };
+        let mut report = report((a, &left), (b, &right), first)?;
+        writeln!(
+            report,
+            "\nThe traces differ by more than {MAX_EDITS} syscalls, so only the first \
+             divergence is reported"
+        )?;
+        return Ok(Some(report.trim_end().to_string()));
+    };
+
+    let mut removed = vec![];
+    let mut added = vec![];
+    let mut returns = vec![];
+    let mut first = None;
+    for edit in &edits {
+        match *edit {
+            Edit::Same(x, y) if left[x].ret != right[y].ret => returns.push((x, y)),
+            Edit::Same(_, _) => continue,
+            Edit::Removed(x) => removed.push(x),
+            Edit::Added(y) => added.push(y),
+        }
+        first.get_or_insert(*edit);
+    }
+
+    let Some(first) = first else {
+        return Ok(None);
+    };
+
+    let mut report = report((a, &left), (b, &right), first)?;
+
+    if !removed.is_empty() {
+        writeln!(report, "\nOnly in {a} ({}):", removed.len())?;
+        for x in removed {
+            writeln!(report, "- {}", left[x])?;
+        }
+    }
+
+    if !added.is_empty() {
+        writeln!(report, "\nOnly in {b} ({}):", added.len())?;
+        for y in added {
+            writeln!(report, "+ {}", right[y])?;
+        }
+    }
+
+    if !returns.is_empty() {
+        writeln!(report, "\nDifferent return values ({}):", returns.len())?;
+        for (x, y) in returns {
+            writeln!(report, "- {}", left[x])?;
+            writeln!(report, "+ {}", right[y])?;
+        }
+    }
+
+    Ok(Some(report.trim_end().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trace of syscalls given as (call, return value)
+    fn trace(syscalls: &[(&str, &str)]) -> Vec<Normalized> {
+        syscalls
+            .iter()
+            .enumerate()
+            .map(|(index, (call, ret))| Normalized {
+                index: index + 1,
+                call: call.to_string(),
+                ret: ret.to_string(),
+            })
+            .collect()
+    }
+
+    const OPEN: (&str, &str) = ("openat(AT_FDCWD, \"/etc/hostname\", 0, 0)", "fd");
+    const READ: (&str, &str) = ("read(fd, addr, 4096)", "9");
+    const CLOSE: (&str, &str) = ("close(fd)", "0");
+    const GETPID: (&str, &str) = ("getpid()", "pid");
+
+    #[test]
+    fn same_traces() {
+        let a = trace(&[OPEN, READ, CLOSE]);
+        let b = trace(&[OPEN, READ, CLOSE]);
+
+        assert_eq!(
+            align(&a, &b),
+            Some(vec![Edit::Same(0, 0), Edit::Same(1, 1), Edit::Same(2, 2)])
+        );
+        assert_eq!(divergence(&a, &b), None);
+    }
+
+    #[test]
+    fn insertion() {
+        let a = trace(&[OPEN, READ, CLOSE]);
+        let b = trace(&[OPEN, GETPID, READ, CLOSE]);
+
+        assert_eq!(
+            align(&a, &b),
+            Some(vec![
+                Edit::Same(0, 0),
+                Edit::Added(1),
+                Edit::Same(1, 2),
+                Edit::Same(2, 3),
+            ])
+        );
+        assert_eq!(divergence(&a, &b), Some(Edit::Same(1, 1)));
+    }
+
+    #[test]
+    fn deletion() {
+        let a = trace(&[OPEN, READ, READ, CLOSE]);
+        let b = trace(&[OPEN, READ, CLOSE]);
+
+        let edits = align(&a, &b).unwrap();
+        assert_eq!(
+            edits
+                .iter()
+                .filter(|edit| matches!(edit, Edit::Removed(_)))
+                .count(),
+            1
+        );
+        assert_eq!(edits.len(), 4);
+        assert_eq!(edits.last(), Some(&Edit::Same(3, 2)));
+        assert_eq!(divergence(&a, &b), Some(Edit::Same(2, 2)));
+    }
+
+    #[test]
+    fn changed_return_value() {
+        let a = trace(&[OPEN, READ, CLOSE]);
+        let b = trace(&[OPEN, ("read(fd, addr, 4096)", "EINTR"), CLOSE]);
+
+        // The calls are the same, so the traces are aligned and only the return value differs
+        assert_eq!(
+            align(&a, &b),
+            Some(vec![Edit::Same(0, 0), Edit::Same(1, 1), Edit::Same(2, 2)])
+        );
+        assert_eq!(divergence(&a, &b), Some(Edit::Same(1, 1)));
+    }
+
+    #[test]
+    fn edits_rebuild_both_traces() {
+        let a = trace(&[OPEN, READ, READ, CLOSE, GETPID, OPEN, CLOSE]);
+        let b = trace(&[GETPID, OPEN, READ, CLOSE, CLOSE, OPEN, READ]);
+
+        let edits = align(&a, &b).unwrap();
+        let left: Vec<usize> = edits
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Same(x, _) | Edit::Removed(x) => Some(*x),
+                Edit::Added(_) => None,
+            })
+            .collect();
+        let right: Vec<usize> = edits
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Same(_, y) | Edit::Added(y) => Some(*y),
+                Edit::Removed(_) => None,
+            })
+            .collect();
+
+        assert_eq!(left, (0..a.len()).collect::<Vec<usize>>());
+        assert_eq!(right, (0..b.len()).collect::<Vec<usize>>());
+        for edit in edits {
+            if let Edit::Same(x, y) = edit {
+                assert_eq!(a[x].call, b[y].call);
+            }
+        }
+    }
+
+    #[test]
+    fn over_budget() {
+        /// Trace of `MAX_EDITS + 1` different syscalls named with `prefix`
+        fn distinct(prefix: &str) -> Vec<Normalized> {
+            (0..=MAX_EDITS)
+                .map(|index| Normalized {
+                    index: index + 1,
+                    call: format!("{prefix}_{index}()"),
+                    ret: "0".to_string(),
+                })
+                .collect()
+        }
+        let (a, b) = (distinct("a"), distinct("b"));
+
+        // Removing all but one syscall is within the budget
+        assert!(align(&a, &a[..1]).is_some());
+        assert_eq!(align(&a, &b), None);
+        assert_eq!(divergence(&a, &b), Some(Edit::Same(0, 0)));
+        assert_eq!(divergence(&a, &a[..1]), Some(Edit::Removed(1)));
+    }
+}
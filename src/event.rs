@@ -0,0 +1,89 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Format of the syscalls written to the standard output or to the file
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per syscall (JSON Lines)
+    Jsonl,
+    /// Lines compatible with the output of strace
+    Strace,
+    /// Trace Event JSON format, loadable in Perfetto or chrome://tracing
+    ChromeTrace,
+    /// Comma separated values with a header, for spreadsheets
+    Csv,
+}
+
+/// Decoded value of a syscall argument
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Signed(i64),
+    Unsigned(u64),
+    String(String),
+}
+
+/// Argument of a syscall, eg: `{"name": "fd", "type": "unsigned int", "value": 3}`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Argument {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: Value,
+}
+
+/// Raw values of the registers at the exit of a syscall
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Registers {
+    pub orig_rax: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rdx: u64,
+    pub r10: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub rax: u64,
+}
+
+/// Value changed by the tracer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tampered {
+    pub argument: String,
+    pub original: u64,
+    pub modified: u64,
+}
+
+/// Structured representation of a syscall, used by the machine readable outputs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Event {
+    /// Process of the thread, the ID of its leader
+    pub pid: i32,
+    /// Thread which made the syscall
+    pub tid: i32,
+    /// Wall clock time in ISO 8601 / RFC 3339 format
+    pub timestamp: String,
+    /// Time of the entry of the syscall from a monotonic clock, in nanoseconds
+    pub entry_ns: u64,
+    /// Time of the exit of the syscall from a monotonic clock, in nanoseconds
+    pub exit_ns: u64,
+    pub duration_ns: u64,
+    pub number: u64,
+    pub name: String,
+    pub args: Vec<Argument>,
+    pub registers: Registers,
+    /// Value returned by the syscall, 0 if it is `unfinished`
+    pub retval: i64,
+    /// Name of the error, eg: `ENOENT`, if the syscall failed
+    pub errno: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub injected: bool,
+    /// The syscall doesn't return, like `exit_group`, so it has no result
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unfinished: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tampered: Vec<Tampered>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub violation: Option<String>,
+}
@@ -0,0 +1,201 @@
+use crate::registers::{argument_name, RegistersData};
+use nix::unistd::Pid;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+/// Value of `AT_FDCWD` as passed in a 32 bits register
+pub const AT_FDCWD: i32 = -100;
+
+/// Commands of `fcntl` which duplicate a file descriptor
+const F_DUPFD: u64 = 0;
+const F_DUPFD_CLOEXEC: u64 = 1030;
+
+/// Check if an argument is a file descriptor, eg: "unsigned int fd" or "int dfd"
+pub fn is_fd(argument: &str) -> bool {
+    !argument.contains('*') && argument_name(argument).ends_with("fd")
+}
+
+/// Check if an argument is a path name, eg: "const char *filename"
+fn is_path(argument: &str) -> bool {
+    argument.contains("char")
+        && argument.contains('*')
+        && matches!(
+            argument_name(argument),
+            "filename" | "pathname" | "path" | "oldname" | "newname"
+        )
+}
+
+/// Make a `path` absolute from `base` and remove every `.` and `..` component, without
+/// following symbolic links
+pub fn normalize(base: &Path, path: &str) -> String {
+    let mut normalized = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+
+    normalized.to_string_lossy().to_string()
+}
+
+/// Paths opened by the file descriptors of a process, shared by the threads created with
+/// `CLONE_FILES`
+type Paths = Rc<RefCell<HashMap<i32, String>>>;
+
+/// Table which keeps the path opened by every file descriptor of the traced processes
+pub struct FdTable {
+    /// The traced processes can be inspected in `/proc`, which is not the case of a loaded trace
+    live: bool,
+    /// Paths of every traced thread
+    threads: HashMap<Pid, Paths>,
+}
+
+impl FdTable {
+    pub fn new(pid: Pid) -> Self {
+        Self {
+            live: true,
+            threads: HashMap::from([(pid, Paths::default())]),
+        }
+    }
+
+    /// Create a table for a loaded trace, which only knows the paths opened during the tracing
+    pub fn offline() -> Self {
+        Self {
+            live: false,
+            threads: HashMap::new(),
+        }
+    }
+
+    /// Give to the `child` created by `parent` a copy of its file descriptors, or the same ones
+    /// if they are shared
+    pub fn fork(&mut self, parent: Pid, child: Pid, shared: bool) {
+        let paths = self.threads.entry(parent).or_default();
+        let paths = if shared {
+            paths.clone()
+        } else {
+            Rc::new(RefCell::new(paths.borrow().clone()))
+        };
+        self.threads.insert(child, paths);
+    }
+
+    /// Forget a thread which exited
+    pub fn exit(&mut self, pid: Pid) {
+        self.threads.remove(&pid);
+    }
+
+    /// Returns the path of a file descriptor of `pid`. If the descriptor has not been opened
+    /// during the tracing, eg: on attach, it is read from `/proc/<pid>/fd/`
+    pub fn path(&self, pid: Pid, fd: i32) -> Option<String> {
+        if let Some(path) = self
+            .threads
+            .get(&pid)
+            .and_then(|paths| paths.borrow().get(&fd).cloned())
+        {
+            return Some(path);
+        }
+
+        if !self.live {
+            return None;
+        }
+        fs::read_link(format!("/proc/{pid}/fd/{fd}"))
+            .ok()
+            .map(|path| path.to_string_lossy().to_string())
+    }
+
+    /// Returns the directory from which a relative path of `pid` is resolved: `dirfd` for the
+    /// `*at` syscalls or the current working directory of the process
+    fn base(&self, pid: Pid, dirfd: Option<i32>) -> PathBuf {
+        match dirfd {
+            Some(fd) if fd != AT_FDCWD => self.path(pid, fd).map(PathBuf::from).unwrap_or_default(),
+            _ if self.live => fs::read_link(format!("/proc/{pid}/cwd")).unwrap_or_default(),
+            _ => PathBuf::new(),
+        }
+    }
+
+    /// Returns the file descriptors passed as argument to the syscall
+    pub fn fds(reg: &RegistersData) -> Vec<i32> {
+        reg.args()
+            .iter()
+            .filter(|(argument, _)| is_fd(argument))
+            .map(|(_, value)| *value as i32)
+            .filter(|fd| *fd >= 0)
+            .collect()
+    }
+
+    /// Returns the absolute paths passed as path name arguments to the syscall
+    pub fn path_args(&self, reg: &RegistersData) -> Vec<String> {
+        let args = reg.args();
+        let dirfd = |name: &str| {
+            args.iter()
+                .find(|(argument, _)| is_fd(argument) && argument_name(argument) == name)
+                .map(|(_, value)| *value as i32)
+        };
+
+        args.iter()
+            .filter(|(argument, _)| is_path(argument))
+            .map(|(argument, value)| {
+                let dirfd = match argument_name(argument) {
+                    "oldname" => dirfd("olddfd"),
+                    "newname" => dirfd("newdfd"),
+                    _ => dirfd("dfd"),
+                };
+                normalize(&self.base(reg.pid(), dirfd), &reg.string(*value))
+            })
+            .collect()
+    }
+
+    /// Returns the absolute paths touched by the syscall, both as path name arguments and as
+    /// file descriptors
+    pub fn paths(&self, reg: &RegistersData) -> Vec<String> {
+        let mut paths = self.path_args(reg);
+        paths.extend(
+            Self::fds(reg)
+                .into_iter()
+                .filter_map(|fd| self.path(reg.pid(), fd)),
+        );
+
+        paths
+    }
+
+    /// Update the table from a syscall at its exit
+    pub fn update(&mut self, reg: &RegistersData) {
+        if reg.errno().is_some() {
+            return;
+        }
+
+        let fd = reg.retval() as i32;
+        let args = reg.args();
+        let arg = |index: usize| args.get(index).map(|(_, value)| *value).unwrap_or_default();
+        let path = match reg.name() {
+            "open" | "openat" | "creat" => self.paths(reg).first().cloned(),
+            "dup" | "dup2" | "dup3" => self.path(reg.pid(), arg(0) as i32),
+            "fcntl" if matches!(arg(1), F_DUPFD | F_DUPFD_CLOEXEC) => {
+                self.path(reg.pid(), arg(0) as i32)
+            }
+            "close" => {
+                if let Some(paths) = self.threads.get(&reg.pid()) {
+                    paths.borrow_mut().remove(&(arg(0) as i32));
+                }
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(path) = path {
+            self.threads
+                .entry(reg.pid())
+                .or_default()
+                .borrow_mut()
+                .insert(fd, path);
+        }
+    }
+}
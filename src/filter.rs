@@ -0,0 +1,572 @@
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+use crate::arch::linux::x86_64::{syscall_name, syscall_number};
+#[cfg(not(all(target_arch = "x86_64", target_os = "linux")))]
+use crate::arch::{syscall_name, syscall_number};
+use crate::{
+    cli::Args,
+    fds::{normalize, FdTable},
+    registers::RegistersData,
+};
+use nix::errno::Errno;
+use regex::Regex;
+use std::{env, str::FromStr};
+
+/// Syscalls which take a file name as argument
+const FILE: &[&str] = &[
+    "open",
+    "stat",
+    "lstat",
+    "access",
+    "execve",
+    "truncate",
+    "chdir",
+    "rename",
+    "mkdir",
+    "rmdir",
+    "creat",
+    "link",
+    "unlink",
+    "symlink",
+    "readlink",
+    "chmod",
+    "chown",
+    "lchown",
+    "utime",
+    "mknod",
+    "uselib",
+    "statfs",
+    "pivot_root",
+    "chroot",
+    "acct",
+    "mount",
+    "umount2",
+    "swapon",
+    "swapoff",
+    "setxattr",
+    "lsetxattr",
+    "getxattr",
+    "lgetxattr",
+    "listxattr",
+    "llistxattr",
+    "removexattr",
+    "lremovexattr",
+    "utimes",
+    "inotify_add_watch",
+    "openat",
+    "mkdirat",
+    "mknodat",
+    "fchownat",
+    "futimesat",
+    "newfstatat",
+    "unlinkat",
+    "renameat",
+    "linkat",
+    "symlinkat",
+    "readlinkat",
+    "fchmodat",
+    "faccessat",
+    "utimensat",
+    "name_to_handle_at",
+    "renameat2",
+    "execveat",
+    "statx",
+    "open_tree",
+    "move_mount",
+    "fspick",
+    "openat2",
+    "faccessat2",
+    "mount_setattr",
+    "fchmodat2",
+    "setxattrat",
+    "getxattrat",
+    "listxattrat",
+    "removexattrat",
+    "open_tree_attr",
+    "file_getattr",
+    "file_setattr",
+];
+
+/// Syscalls related to sockets
+const NETWORK: &[&str] = &[
+    "socket",
+    "connect",
+    "accept",
+    "sendto",
+    "recvfrom",
+    "sendmsg",
+    "recvmsg",
+    "shutdown",
+    "bind",
+    "listen",
+    "getsockname",
+    "getpeername",
+    "socketpair",
+    "setsockopt",
+    "getsockopt",
+    "accept4",
+    "recvmmsg",
+    "sendmmsg",
+];
+
+/// Syscalls related to the process lifecycle
+const PROCESS: &[&str] = &[
+    "clone",
+    "fork",
+    "vfork",
+    "execve",
+    "exit",
+    "wait4",
+    "kill",
+    "exit_group",
+    "tkill",
+    "tgkill",
+    "waitid",
+    "rt_sigqueueinfo",
+    "rt_tgsigqueueinfo",
+    "execveat",
+    "clone3",
+];
+
+/// Syscalls related to memory mapping
+const MEMORY: &[&str] = &[
+    "brk",
+    "mmap",
+    "munmap",
+    "mprotect",
+    "mremap",
+    "msync",
+    "mincore",
+    "madvise",
+    "shmat",
+    "shmdt",
+    "mlock",
+    "munlock",
+    "mlockall",
+    "munlockall",
+    "remap_file_pages",
+    "mbind",
+    "set_mempolicy",
+    "get_mempolicy",
+    "migrate_pages",
+    "move_pages",
+    "mlock2",
+    "pkey_mprotect",
+    "process_madvise",
+    "set_mempolicy_home_node",
+    "map_shadow_stack",
+    "mseal",
+];
+
+/// Syscalls related to signals
+const SIGNAL: &[&str] = &[
+    "rt_sigaction",
+    "rt_sigprocmask",
+    "rt_sigreturn",
+    "kill",
+    "pause",
+    "rt_sigpending",
+    "rt_sigtimedwait",
+    "rt_sigqueueinfo",
+    "rt_sigsuspend",
+    "sigaltstack",
+    "tkill",
+    "tgkill",
+    "signalfd",
+    "signalfd4",
+    "rt_tgsigqueueinfo",
+    "pidfd_send_signal",
+];
+
+/// Syscalls related to System V IPC
+const IPC: &[&str] = &[
+    "shmget",
+    "shmat",
+    "shmctl",
+    "semget",
+    "semop",
+    "semctl",
+    "shmdt",
+    "msgget",
+    "msgsnd",
+    "msgrcv",
+    "msgctl",
+    "semtimedop",
+];
+
+/// Syscalls which take or return a file descriptor
+const DESC: &[&str] = &[
+    "read",
+    "write",
+    "open",
+    "close",
+    "fstat",
+    "poll",
+    "lseek",
+    "mmap",
+    "ioctl",
+    "pread64",
+    "pwrite64",
+    "readv",
+    "writev",
+    "pipe",
+    "select",
+    "dup",
+    "dup2",
+    "sendfile",
+    "fcntl",
+    "flock",
+    "fsync",
+    "fdatasync",
+    "ftruncate",
+    "getdents",
+    "fchdir",
+    "creat",
+    "fchmod",
+    "fchown",
+    "fstatfs",
+    "readahead",
+    "fsetxattr",
+    "fgetxattr",
+    "flistxattr",
+    "fremovexattr",
+    "epoll_create",
+    "getdents64",
+    "fadvise64",
+    "epoll_wait",
+    "epoll_ctl",
+    "mq_open",
+    "mq_timedsend",
+    "mq_timedreceive",
+    "mq_notify",
+    "mq_getsetattr",
+    "inotify_init",
+    "inotify_add_watch",
+    "inotify_rm_watch",
+    "openat",
+    "mkdirat",
+    "mknodat",
+    "fchownat",
+    "futimesat",
+    "newfstatat",
+    "unlinkat",
+    "renameat",
+    "linkat",
+    "symlinkat",
+    "readlinkat",
+    "fchmodat",
+    "faccessat",
+    "pselect6",
+    "ppoll",
+    "splice",
+    "tee",
+    "sync_file_range",
+    "vmsplice",
+    "utimensat",
+    "epoll_pwait",
+    "signalfd",
+    "timerfd_create",
+    "eventfd",
+    "fallocate",
+    "timerfd_settime",
+    "timerfd_gettime",
+    "signalfd4",
+    "eventfd2",
+    "epoll_create1",
+    "dup3",
+    "pipe2",
+    "inotify_init1",
+    "preadv",
+    "pwritev",
+    "perf_event_open",
+    "fanotify_init",
+    "fanotify_mark",
+    "name_to_handle_at",
+    "open_by_handle_at",
+    "syncfs",
+    "setns",
+    "finit_module",
+    "renameat2",
+    "memfd_create",
+    "kexec_file_load",
+    "bpf",
+    "execveat",
+    "userfaultfd",
+    "copy_file_range",
+    "preadv2",
+    "pwritev2",
+    "statx",
+    "pidfd_send_signal",
+    "io_uring_setup",
+    "io_uring_enter",
+    "io_uring_register",
+    "open_tree",
+    "move_mount",
+    "fsopen",
+    "fsconfig",
+    "fsmount",
+    "fspick",
+    "pidfd_open",
+    "close_range",
+    "openat2",
+    "pidfd_getfd",
+    "faccessat2",
+    "process_madvise",
+    "epoll_pwait2",
+    "mount_setattr",
+    "quotactl_fd",
+    "landlock_create_ruleset",
+    "landlock_add_rule",
+    "landlock_restrict_self",
+    "memfd_secret",
+    "process_mrelease",
+    "cachestat",
+    "fchmodat2",
+    "setxattrat",
+    "getxattrat",
+    "listxattrat",
+    "removexattrat",
+    "open_tree_attr",
+    "file_getattr",
+    "file_setattr",
+];
+
+/// Returns the list of syscalls for a class name like `%file`
+fn class(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        "file" => Some(FILE),
+        "network" | "net" => Some(NETWORK),
+        "process" => Some(PROCESS),
+        "memory" => Some(MEMORY),
+        "signal" => Some(SIGNAL),
+        "ipc" => Some(IPC),
+        "desc" => Some(DESC),
+        _ => None,
+    }
+}
+
+/// What a single term of a filter expression matches against
+#[derive(Clone, Debug)]
+enum Matcher {
+    /// Exact syscall name, eg: `openat`
+    Name(String),
+    /// Class of syscalls, eg: `%file`
+    Class(&'static [&'static str]),
+    /// Regular expression over the syscall name, eg: `/^stat/`
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Matcher::Name(n) => n == name,
+            Matcher::Class(names) => names.contains(&name),
+            Matcher::Regex(re) => re.is_match(name),
+        }
+    }
+}
+
+/// A term of a filter expression which can be negated with a `!` prefix
+#[derive(Clone, Debug)]
+struct Term {
+    negated: bool,
+    matcher: Matcher,
+}
+
+impl FromStr for Term {
+    type Err = String;
+
+    fn from_str(term: &str) -> Result<Self, Self::Err> {
+        let (negated, term) = match term.strip_prefix('!') {
+            Some(term) => (true, term.trim()),
+            None => (false, term),
+        };
+
+        let matcher = if let Some(name) = term.strip_prefix('%') {
+            Matcher::Class(class(name).ok_or(format!("unknown syscall class `%{name}`"))?)
+        } else if term.len() > 1 && term.starts_with('/') && term.ends_with('/') {
+            let re = Regex::new(&term[1..term.len() - 1])
+                .map_err(|e| format!("invalid regex `{term}`: {e}"))?;
+            Matcher::Regex(re)
+        } else if syscall_number(term).is_some() {
+            Matcher::Name(term.to_string())
+        } else if let Some(name) = term
+            .parse::<u64>()
+            .ok()
+            .map(|nro| syscall_name(nro).to_string())
+            .filter(|name| !name.is_empty())
+        {
+            Matcher::Name(name)
+        } else {
+            return Err(format!("unknown syscall `{term}`"));
+        };
+
+        Ok(Term { negated, matcher })
+    }
+}
+
+/// Filter expression used to select which syscalls have to be shown.
+///
+/// The expression is a list of terms separated by comma `,`. A term can be a syscall name
+/// (`openat`) or number (`257`), a class (`%file`, `%network`, `%process`, `%memory`,
+/// `%signal`, `%ipc`, `%desc`) or a regex between slashes (`/^stat/`). Every term can be negated
+/// with a `!` prefix.
+/// A syscall is shown if it matches at least one positive term (or there is not any positive
+/// term) and it does not match any negated term.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    terms: Vec<Term>,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let terms = expression
+            .split(',')
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+            .map(Term::from_str)
+            .collect::<Result<Vec<Term>, String>>()?;
+
+        if terms.is_empty() {
+            return Err("empty filter expression".to_string());
+        }
+
+        Ok(Filter { terms })
+    }
+}
+
+impl Filter {
+    /// Check if a syscall `name` is selected by the filter
+    pub fn matches(&self, name: &str) -> bool {
+        let mut has_positive = false;
+        let mut selected = false;
+
+        for term in &self.terms {
+            if term.negated {
+                if term.matcher.matches(name) {
+                    return false;
+                }
+            } else {
+                has_positive = true;
+                selected |= term.matcher.matches(name);
+            }
+        }
+
+        !has_positive || selected
+    }
+}
+
+/// Parse an errno from its name, eg: `ENOENT`, or from its number
+pub fn parse_errno(value: &str) -> Result<Errno, String> {
+    if let Ok(nro) = value.parse::<i32>() {
+        return match Errno::from_i32(nro) {
+            Errno::UnknownErrno => Err(format!("unknown errno `{value}`")),
+            errno => Ok(errno),
+        };
+    }
+
+    (1..4096)
+        .map(Errno::from_i32)
+        .find(|errno| *errno != Errno::UnknownErrno && format!("{errno:?}") == value)
+        .ok_or(format!("unknown errno `{value}`"))
+}
+
+/// Parse a path making it absolute from the current directory
+pub fn parse_path(value: &str) -> Result<String, String> {
+    let cwd = env::current_dir().map_err(|e| e.to_string())?;
+    Ok(normalize(&cwd, value))
+}
+
+/// Check if a line, taken at the exit of its syscall, has to be shown according to the filters
+/// defined by `args`. The `fds` table must not be updated yet with this line
+pub fn is_selected(args: &Args, reg: &RegistersData, fds: &FdTable) -> bool {
+    if let Some(filter) = &args.filter {
+        if !filter.matches(reg.name()) {
+            return false;
+        }
+    }
+
+    let errno = reg.errno();
+    if args.failed_only && errno.is_none() {
+        return false;
+    }
+
+    if args.successful_only && errno.is_some() {
+        return false;
+    }
+
+    if !args.errno.is_empty() && !errno.is_some_and(|errno| args.errno.contains(&errno)) {
+        return false;
+    }
+
+    if !args.fd.is_empty() && !FdTable::fds(reg).iter().any(|fd| args.fd.contains(fd)) {
+        return false;
+    }
+
+    if !args.predicates.is_empty()
+        && !args
+            .predicates
+            .iter()
+            .any(|predicate| predicate.matches(reg))
+    {
+        return false;
+    }
+
+    if !args.path.is_empty() && !fds.paths(reg).iter().any(|path| args.path.contains(path)) {
+        return false;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selected(expression: &str) -> Vec<&'static str> {
+        let filter = Filter::from_str(expression).unwrap();
+        ["openat", "read", "write", "stat", "statx", "mmap", "getpid"]
+            .into_iter()
+            .filter(|name| filter.matches(name))
+            .collect()
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(selected("read"), ["read"]);
+        assert_eq!(selected("read, write"), ["read", "write"]);
+        assert_eq!(selected("257,0"), ["openat", "read"]);
+        assert_eq!(selected("/^stat/"), ["stat", "statx"]);
+        assert_eq!(selected("%memory"), ["mmap"]);
+        assert_eq!(selected("%file,!stat"), ["openat", "statx"]);
+        assert_eq!(
+            selected("!read,!/^stat/"),
+            ["openat", "write", "mmap", "getpid"]
+        );
+        assert_eq!(selected("! getpid,getpid"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        let error = |expression: &str| Filter::from_str(expression).unwrap_err();
+
+        assert_eq!(error(""), "empty filter expression");
+        assert_eq!(error(" , "), "empty filter expression");
+        assert_eq!(error("nosuchcall"), "unknown syscall `nosuchcall`");
+        assert_eq!(error("read,999"), "unknown syscall `999`");
+        assert_eq!(
+            error("%nosuchclass"),
+            "unknown syscall class `%nosuchclass`"
+        );
+        assert!(error("/(/").starts_with("invalid regex `/(/`"));
+    }
+
+    #[test]
+    fn errnos() {
+        assert_eq!(parse_errno("ENOENT"), Ok(Errno::ENOENT));
+        assert_eq!(parse_errno("13"), Ok(Errno::EACCES));
+        assert!(parse_errno("ENOTANERROR").is_err());
+        assert!(parse_errno("0").is_err());
+    }
+}
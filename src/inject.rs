@@ -0,0 +1,470 @@
+use crate::{
+    filter::{parse_errno, Filter},
+    predicate::parse_number,
+    registers::{argument_name, RegistersData},
+};
+use nix::{errno::Errno, libc::user_regs_struct, sys::ptrace, unistd::Pid};
+use rand::Rng;
+use std::{collections::HashMap, str::FromStr, thread, time::Duration};
+
+/// Invocations of a syscall on which an injection is applied, counting from 1
+#[derive(Clone, Debug)]
+struct When {
+    first: usize,
+    /// If `None` only the `first` invocation is selected, else every `step` invocations after
+    /// the `first` one
+    step: Option<usize>,
+}
+
+impl Default for When {
+    fn default() -> Self {
+        When {
+            first: 1,
+            step: Some(1),
+        }
+    }
+}
+
+impl FromStr for When {
+    type Err = String;
+
+    /// Parse `N`, `N+` or `N+step`
+    fn from_str(when: &str) -> Result<Self, Self::Err> {
+        let number = |value: &str| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or(format!("invalid `when={when}`"))
+        };
+
+        match when.split_once('+') {
+            Some((first, "")) => Ok(When {
+                first: number(first)?,
+                step: Some(1),
+            }),
+            Some((first, step)) => Ok(When {
+                first: number(first)?,
+                step: Some(number(step)?),
+            }),
+            None => Ok(When {
+                first: number(when)?,
+                step: None,
+            }),
+        }
+    }
+}
+
+impl When {
+    fn matches(&self, invocation: usize) -> bool {
+        match self.step {
+            _ if invocation == self.first => true,
+            Some(step) => invocation > self.first && (invocation - self.first).is_multiple_of(step),
+            None => false,
+        }
+    }
+}
+
+/// Rule which makes the selected syscalls fail or which tampers their values, formatted like
+/// `syscall:ACTION[:ACTION]...[:when=N[+[step]]][:probability=P]`, where `syscall` is a filter
+/// expression (eg: `openat`, `%file`) and `P` is a number in `0..=1` or a percentage.
+/// An `ACTION` can be:
+/// - `error=ERRNO`: skip the syscall and return the error
+/// - `retval=N`: run the syscall and return `N`
+/// - `argI=N`: run the syscall with the argument at index `I` (from 0 to 5) set to `N`
+#[derive(Clone, Debug)]
+pub struct Injection {
+    filter: Filter,
+    error: Option<Errno>,
+    retval: Option<i64>,
+    args: Vec<(usize, u64)>,
+    when: When,
+    probability: f64,
+}
+
+impl FromStr for Injection {
+    type Err = String;
+
+    fn from_str(injection: &str) -> Result<Self, Self::Err> {
+        let mut parts = injection.split(':');
+        let filter = Filter::from_str(parts.next().unwrap_or_default())?;
+
+        let number = |value: &str| parse_number(value).ok_or(format!("`{value}` is not a number"));
+
+        let mut error = None;
+        let mut retval = None;
+        let mut args = vec![];
+        let mut when = When::default();
+        let mut probability = 1.0;
+        for part in parts {
+            match part.split_once('=') {
+                Some(("error", value)) => error = Some(parse_errno(value)?),
+                Some(("retval", value)) => retval = Some(number(value)?),
+                Some(("when", value)) => when = When::from_str(value)?,
+                Some(("probability", value)) => {
+                    probability = match value.strip_suffix('%') {
+                        Some(value) => value.parse::<f64>().map(|p| p / 100.0),
+                        None => value.parse::<f64>(),
+                    }
+                    .ok()
+                    .filter(|p| (0.0..=1.0).contains(p))
+                    .ok_or(format!("invalid `probability={value}`"))?;
+                }
+                Some((arg, value)) if arg.starts_with("arg") => {
+                    let index = arg[3..]
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|index| *index < 6)
+                        .ok_or(format!(
+                            "invalid argument `{arg}`, it must be `arg0`..`arg5`"
+                        ))?;
+                    args.push((index, number(value)? as u64));
+                }
+                _ => return Err(format!("invalid injection option `{part}`")),
+            }
+        }
+
+        if error.is_some() && retval.is_some() {
+            return Err(format!(
+                "`error=` and `retval=` can not be used together in `{injection}`"
+            ));
+        }
+
+        if error.is_none() && retval.is_none() && args.is_empty() {
+            return Err(format!(
+                "missing `error=`, `retval=` or `argI=` in `{injection}`"
+            ));
+        }
+
+        Ok(Injection {
+            filter,
+            error,
+            retval,
+            args,
+            when,
+            probability,
+        })
+    }
+}
+
+/// Rule which delays the selected syscalls, formatted like `syscall:enter=DURATION` and/or
+/// `syscall:exit=DURATION`, where `syscall` is a filter expression (eg: `read`, `%network`)
+/// and `DURATION` is like `200ms` or `1s`.
+#[derive(Clone, Debug)]
+pub struct Delay {
+    filter: Filter,
+    enter: Option<Duration>,
+    exit: Option<Duration>,
+}
+
+impl FromStr for Delay {
+    type Err = String;
+
+    fn from_str(delay: &str) -> Result<Self, Self::Err> {
+        let mut parts = delay.split(':');
+        let filter = Filter::from_str(parts.next().unwrap_or_default())?;
+
+        let duration = |value: &str| {
+            humantime::parse_duration(value).map_err(|e| format!("invalid duration `{value}`: {e}"))
+        };
+
+        let mut enter = None;
+        let mut exit = None;
+        for part in parts {
+            match part.split_once('=') {
+                Some(("enter", value)) => enter = Some(duration(value)?),
+                Some(("exit", value)) => exit = Some(duration(value)?),
+                _ => return Err(format!("invalid delay option `{part}`")),
+            }
+        }
+
+        if enter.is_none() && exit.is_none() {
+            return Err(format!("missing `enter=` or `exit=` in `{delay}`"));
+        }
+
+        Ok(Delay {
+            filter,
+            enter,
+            exit,
+        })
+    }
+}
+
+/// Changes made by an injection at the entry of a syscall, completed at its exit
+struct Pending {
+    /// Original syscall number if the syscall has been skipped
+    skipped: Option<u64>,
+    error: Option<Errno>,
+    retval: Option<i64>,
+    /// Arguments changed at the entry as (argument, original value, modified value)
+    args: Vec<(String, u64, u64)>,
+    /// Registers before the changes, to restore the arguments at the exit as the kernel
+    /// preserves them across a syscall
+    registers: user_regs_struct,
+}
+
+/// Returns the register used for the argument at `index`
+fn argument_register(regs: &mut user_regs_struct, index: usize) -> &mut u64 {
+    match index {
+        0 => &mut regs.rdi,
+        1 => &mut regs.rsi,
+        2 => &mut regs.rdx,
+        3 => &mut regs.r10,
+        4 => &mut regs.r8,
+        _ => &mut regs.r9,
+    }
+}
+
+/// Apply the injections and the delays to a traced process, keeping how many times each
+/// injection has been matched
+pub struct Injector {
+    injections: Vec<Injection>,
+    delays: Vec<Delay>,
+    invocations: Vec<usize>,
+    /// Injections made at the entry of the current syscall of every thread
+    pending: HashMap<Pid, Pending>,
+}
+
+impl Injector {
+    pub fn new(injections: &[Injection], delays: &[Delay]) -> Self {
+        Self {
+            injections: injections.to_vec(),
+            delays: delays.to_vec(),
+            invocations: vec![0; injections.len()],
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Sleep for the delays of the syscall `name` before resuming the process
+    fn delay(&self, name: &str, stop: fn(&Delay) -> Option<Duration>) {
+        let duration: Duration = self
+            .delays
+            .iter()
+            .filter(|delay| delay.filter.matches(name))
+            .filter_map(stop)
+            .sum();
+
+        if !duration.is_zero() {
+            thread::sleep(duration);
+        }
+    }
+
+    /// At the entry of a syscall, apply the first injection which matches it and fires on this
+    /// invocation: change its arguments and skip it setting `orig_rax` to -1 when it has to fail.
+    /// Every matching injection counts the invocation, even if an earlier one fired
+    pub fn enter(&mut self, reg: &RegistersData) -> anyhow::Result<()> {
+        self.pending.remove(&reg.pid());
+        self.delay(reg.name(), |delay| delay.enter);
+
+        let mut fired = None;
+        for (index, injection) in self.injections.iter().enumerate() {
+            if !injection.filter.matches(reg.name()) {
+                continue;
+            }
+
+            self.invocations[index] += 1;
+            if fired.is_none()
+                && injection.when.matches(self.invocations[index])
+                && rand::thread_rng().gen_bool(injection.probability)
+            {
+                fired = Some(injection);
+            }
+        }
+        let Some(injection) = fired else {
+            return Ok(());
+        };
+
+        let mut regs = ptrace::getregs(reg.pid())?;
+        let mut pending = Pending {
+            skipped: None,
+            error: injection.error,
+            retval: injection.retval,
+            args: vec![],
+            registers: regs,
+        };
+
+        for (index, value) in &injection.args {
+            let register = argument_register(&mut regs, *index);
+            let argument = match argument_name(reg.argument(*index)) {
+                "" => format!("arg{index}"),
+                name => name.to_string(),
+            };
+            pending.args.push((argument, *register, *value));
+            *register = *value;
+        }
+
+        if injection.error.is_some() {
+            pending.skipped = Some(regs.orig_rax);
+            regs.orig_rax = u64::MAX;
+        }
+
+        ptrace::setregs(reg.pid(), regs)?;
+        self.pending.insert(reg.pid(), pending);
+
+        Ok(())
+    }
+
+    /// At the entry of a syscall, skip it making it fail with `error` regardless of the
+    /// injections
+    pub fn deny(&mut self, reg: &RegistersData, error: Errno) -> anyhow::Result<()> {
+        let mut regs = ptrace::getregs(reg.pid())?;
+        self.pending.insert(
+            reg.pid(),
+            Pending {
+                skipped: Some(regs.orig_rax),
+                error: Some(error),
+                retval: None,
+                args: vec![],
+                registers: regs,
+            },
+        );
+        regs.orig_rax = u64::MAX;
+        ptrace::setregs(reg.pid(), regs)?;
+
+        Ok(())
+    }
+
+    /// Returns if the current syscall of `pid` has been skipped by `enter` or `deny`
+    pub fn skipped(&self, pid: Pid) -> bool {
+        self.pending
+            .get(&pid)
+            .is_some_and(|pending| pending.skipped.is_some())
+    }
+
+    /// At the exit of a syscall changed by `enter`, set the error or the value to return
+    pub fn exit(&mut self, reg: &mut RegistersData) -> anyhow::Result<()> {
+        let Some(pending) = self.pending.remove(&reg.pid()) else {
+            self.delay(reg.name(), |delay| delay.exit);
+            return Ok(());
+        };
+
+        let mut regs = ptrace::getregs(reg.pid())?;
+        let rax = regs.rax;
+        let mut original = pending.registers;
+        for index in 0..6 {
+            *argument_register(&mut regs, index) = *argument_register(&mut original, index);
+        }
+        if let Some(error) = pending.error {
+            regs.rax = -(error as i64) as u64;
+        } else if let Some(retval) = pending.retval {
+            regs.rax = retval as u64;
+        }
+        ptrace::setregs(reg.pid(), regs)?;
+
+        if let Some(orig_rax) = pending.skipped {
+            regs.orig_rax = orig_rax;
+        }
+
+        let process = reg.process();
+        *reg = RegistersData::new(reg.pid(), regs);
+        reg.set_process(process);
+        if pending.skipped.is_some() {
+            reg.set_injected();
+        }
+        for (argument, original, modified) in pending.args {
+            reg.set_tampered(argument, original, modified);
+        }
+        if pending.retval.is_some() {
+            reg.set_tampered("ret".to_string(), rax, regs.rax);
+        }
+        self.delay(reg.name(), |delay| delay.exit);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn when(when: &str) -> Vec<usize> {
+        let when = When::from_str(when).unwrap();
+        (1..=10)
+            .filter(|invocation| when.matches(*invocation))
+            .collect()
+    }
+
+    #[test]
+    fn invocations() {
+        assert_eq!(when("3"), vec![3]);
+        assert_eq!(when("8+"), vec![8, 9, 10]);
+        assert_eq!(when("2+3"), vec![2, 5, 8]);
+        assert_eq!(
+            (1..=10)
+                .filter(|invocation| When::default().matches(*invocation))
+                .count(),
+            10
+        );
+
+        for invalid in ["", "0", "-1", "+", "3+0", "x", "2+y"] {
+            assert!(When::from_str(invalid).is_err(), "`{invalid}` is valid");
+        }
+    }
+
+    #[test]
+    fn injections() {
+        let injection = Injection::from_str("openat:error=ENOENT:when=3+").unwrap();
+        assert!(injection.filter.matches("openat"));
+        assert!(!injection.filter.matches("read"));
+        assert_eq!(injection.error, Some(Errno::ENOENT));
+        assert_eq!(injection.retval, None);
+        assert!(!injection.when.matches(2) && injection.when.matches(3));
+        assert_eq!(injection.probability, 1.0);
+
+        let injection = Injection::from_str("write:arg2=1:retval=0x10:probability=25%").unwrap();
+        assert_eq!(injection.args, vec![(2, 1)]);
+        assert_eq!(injection.retval, Some(16));
+        assert_eq!(injection.probability, 0.25);
+
+        let injection = Injection::from_str("%file:arg0=-1:probability=0.5").unwrap();
+        assert!(injection.filter.matches("openat"));
+        assert_eq!(injection.args, vec![(0, u64::MAX)]);
+        assert_eq!(injection.probability, 0.5);
+    }
+
+    #[test]
+    fn invalid_injections() {
+        let error = |injection: &str| Injection::from_str(injection).unwrap_err();
+
+        assert_eq!(
+            error("openat"),
+            "missing `error=`, `retval=` or `argI=` in `openat`"
+        );
+        assert_eq!(
+            error("openat:error=ENOENT:retval=0"),
+            "`error=` and `retval=` can not be used together in `openat:error=ENOENT:retval=0`"
+        );
+        assert_eq!(
+            error("write:arg6=1"),
+            "invalid argument `arg6`, it must be `arg0`..`arg5`"
+        );
+        assert_eq!(error("write:retval=many"), "`many` is not a number");
+        assert_eq!(error("write:retval=0:when=0"), "invalid `when=0`");
+        assert_eq!(
+            error("write:retval=0:probability=2"),
+            "invalid `probability=2`"
+        );
+        assert_eq!(
+            error("write:retval=0:probability=150%"),
+            "invalid `probability=150%`"
+        );
+        assert_eq!(
+            error("write:retval=0:often"),
+            "invalid injection option `often`"
+        );
+        assert!(Injection::from_str("write:error=ENOTANERROR").is_err());
+        assert!(Injection::from_str("nosuchcall:retval=0").is_err());
+    }
+
+    #[test]
+    fn delays() {
+        let delay = Delay::from_str("read:enter=200ms:exit=1s").unwrap();
+        assert!(delay.filter.matches("read"));
+        assert_eq!(delay.enter, Some(Duration::from_millis(200)));
+        assert_eq!(delay.exit, Some(Duration::from_secs(1)));
+
+        assert!(Delay::from_str("read").is_err());
+        assert!(Delay::from_str("read:enter=soon").is_err());
+        assert!(Delay::from_str("read:after=1s").is_err());
+    }
+}
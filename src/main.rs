@@ -1,53 +1,99 @@
 mod arch;
+mod chrome;
 mod cli;
+mod csv;
+mod diff;
+mod event;
+mod fds;
+mod filter;
+mod inject;
+mod output;
+mod policy;
+mod predicate;
+mod record;
 mod registers;
+mod replay;
+mod stack;
+mod stats;
+mod strace;
+mod symbols;
 mod trace;
 mod ui;
 
-use crate::cli::Args;
+use crate::cli::{Args, Tool};
+use crate::diff::diff;
+use crate::replay::replay;
 use crate::trace::{exec, trace};
 use crate::ui::UI;
 
 use clap::Parser;
 use fork::{fork, Fork};
 use nix::unistd::Pid;
-use owo_colors::OwoColorize;
+use owo_colors::{OwoColorize, Style};
+use std::{
+    io::{self, IsTerminal},
+    process,
+};
 use trace::attach;
 
 /// Create a fork of the program and execute the process in the child. Parent gets the pid
 /// value and trace it.
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
-    let process: Result<Pid, String> = if args.command.is_some() {
+    if let Some(Tool::Diff { a, b }) = &args.tool {
+        match diff(a, b, &args)? {
+            Some(report) => {
+                println!("{report}");
+                process::exit(1);
+            }
+            None => {
+                println!("The traces are equivalent");
+                return Ok(());
+            }
+        }
+    }
+
+    // No process is traced when a trace is loaded
+    let process: Result<Option<Pid>, String> = if args.load.is_some() {
+        Ok(None)
+    } else if let Some(command) = &args.command {
         match fork() {
-            Ok(Fork::Child) => return exec(&args.command.unwrap()),
-            Ok(Fork::Parent(child)) => Ok(Pid::from_raw(child)),
+            Ok(Fork::Child) => return exec(command),
+            Ok(Fork::Parent(child)) => Ok(Some(Pid::from_raw(child))),
             Err(err) => Err(format!("fork() failed: {err}")),
         }
-    } else if args.attach.is_some() {
-        let pid = Pid::from_raw(args.attach.unwrap());
+    } else if let Some(pid) = args.attach {
+        let pid = Pid::from_raw(pid);
 
         if attach(pid).is_ok() {
-            Ok(pid)
+            Ok(Some(pid))
         } else {
             Err(format!("Unable to attach to process `{pid}`"))
         }
     } else {
-        Err(format!("You must define a command or a PID to attach"))
+        Err("You must define a command or a PID to attach".to_string())
     };
 
     match process {
         Ok(pid) => {
-            if !args.no_tui {
+            if !args.no_tui && !args.summary_only {
                 let mut ui = UI::new();
 
                 ui.start(pid, &args)?;
-            } else {
+            } else if let Some(pid) = pid {
                 trace(pid, &args)?;
+            } else if let Some(path) = &args.load {
+                replay(path, &args)?;
             }
         }
         Err(e) => {
-            eprintln!("{}", e.red());
+            let color = args.color.enabled(io::stderr().is_terminal());
+            let style = if color {
+                Style::new().red()
+            } else {
+                Style::new()
+            };
+            eprintln!("{}", e.style(style));
         }
     };
 
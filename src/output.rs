@@ -0,0 +1,391 @@
+use crate::{cli::Args, event::OutputFormat, predicate::parse_number};
+use flate2::{write::GzEncoder, Compression};
+use nix::unistd::Pid;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    process::{Command, ExitStatus, Stdio},
+    sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Lines waiting to be read by the command of `--pipe-to`
+const PIPE_CAPACITY: usize = 4096;
+
+/// Time the tracee waits for the command of `--pipe-to` to read a line when the pipe is full,
+/// before dropping it
+const PIPE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Parse a size in bytes with a binary suffix, eg: `100M`
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    match parse_number(value) {
+        Some(size) if size > 0 => Ok(size as u64),
+        _ => Err(format!("invalid size `{value}`")),
+    }
+}
+
+/// Compress the file at `path` to `path.gz`, removing it
+fn compress(path: &str) -> io::Result<()> {
+    let mut encoder = GzEncoder::new(File::create(format!("{path}.gz"))?, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+
+    fs::remove_file(path)
+}
+
+/// File receiving the output, rotated as `FILE.1` (the newest) to `FILE.N` when it reaches
+/// `--max-size`
+pub struct OutputFile {
+    path: String,
+    file: File,
+    /// Bytes written to `file`
+    written: u64,
+    max_size: Option<u64>,
+    max_files: usize,
+    compress: bool,
+    /// Line written at the start of every file, like the header of a CSV
+    header: Option<String>,
+    /// Compression of the last rotated file, done in the background to not stop the tracee
+    compression: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl OutputFile {
+    /// Create the output file defined by `--file`
+    pub fn create(path: &str, args: &Args) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_string(),
+            file: File::create(path)?,
+            written: 0,
+            max_size: args.max_size,
+            max_files: args.max_files,
+            compress: args.compress,
+            header: None,
+            compression: None,
+        })
+    }
+
+    /// Write `header` now and at the start of every rotated file
+    pub fn header(&mut self, header: &str) -> io::Result<()> {
+        self.header = Some(header.to_string());
+        self.write_line(header)
+    }
+
+    /// Write a line, rotating the file before if the line would exceed `--max-size`
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let length = line.len() as u64 + 1;
+        if self
+            .max_size
+            .is_some_and(|max_size| self.written > 0 && self.written + length > max_size)
+        {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{line}")?;
+        self.written += length;
+
+        Ok(())
+    }
+
+    /// Returns the path of the rotated file `index`
+    fn rotated(&self, index: usize) -> String {
+        if self.compress {
+            format!("{}.{index}.gz", self.path)
+        } else {
+            format!("{}.{index}", self.path)
+        }
+    }
+
+    /// Shift the rotated files dropping the oldest one, move the file to `FILE.1` and start a
+    /// new one
+    fn rotate(&mut self) -> io::Result<()> {
+        self.wait_compression()?;
+
+        if self.max_files > 0 {
+            let _ = fs::remove_file(self.rotated(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let _ = fs::rename(self.rotated(index), self.rotated(index + 1));
+            }
+
+            let first = format!("{}.1", self.path);
+            fs::rename(&self.path, &first)?;
+            if self.compress {
+                self.compression = Some(thread::spawn(move || compress(&first)));
+            }
+        }
+
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        if let Some(header) = self.header.clone() {
+            writeln!(self.file, "{header}")?;
+            self.written += header.len() as u64 + 1;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the compression of the last rotated file
+    fn wait_compression(&mut self) -> io::Result<()> {
+        match self.compression.take() {
+            Some(compression) => compression
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("compression panicked"))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        let _ = self.wait_compression();
+    }
+}
+
+/// Run `command` with a shell and write the lines received from the tracer to its standard
+/// input, then wait for it. The command is a child of the thread running this, so the tracer,
+/// which waits for its tracees and the children of its own thread, never reaps it
+fn feed(
+    command: &str,
+    lines: Receiver<String>,
+    spawned: Sender<io::Result<()>>,
+) -> io::Result<ExitStatus> {
+    let mut child = match Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            let _ = spawned.send(Err(err));
+            return Err(io::Error::other("the command could not be spawned"));
+        }
+    };
+    let _ = spawned.send(Ok(()));
+
+    // The lines which can't be written anymore are dropped by `Pipe` when the channel closes
+    if let Some(stdin) = child.stdin.take() {
+        let _ = write_lines(lines, stdin);
+    }
+
+    child.wait()
+}
+
+/// Write the lines received from the tracer to `stdin`, flushing them when there are no more
+/// waiting
+fn write_lines(lines: Receiver<String>, stdin: impl Write) -> io::Result<()> {
+    let mut stdin = BufWriter::new(stdin);
+    while let Ok(line) = lines.recv() {
+        writeln!(stdin, "{line}")?;
+        for line in lines.try_iter() {
+            writeln!(stdin, "{line}")?;
+        }
+        stdin.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Command receiving the output on its standard input. The lines are written by a thread, so a
+/// slow command only stops the tracee for `PIPE_TIMEOUT` when the pipe is full, and the lines
+/// which don't fit are dropped until it empties
+pub struct Pipe {
+    command: String,
+    lines: Option<SyncSender<String>>,
+    /// Thread running the command with `feed`, returning its exit status
+    feeder: Option<JoinHandle<io::Result<ExitStatus>>>,
+    /// The last line has been dropped, so the next ones are dropped without waiting
+    overflowing: bool,
+    dropped: usize,
+}
+
+impl Pipe {
+    /// Run `command` with a shell
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(PIPE_CAPACITY);
+        let (spawned, started) = mpsc::channel();
+
+        let shell = command.to_string();
+        let feeder = thread::spawn(move || feed(&shell, receiver, spawned));
+        started
+            .recv()
+            .unwrap_or_else(|_| Err(io::Error::other("the feeder of the pipe panicked")))?;
+
+        Ok(Self {
+            command: command.to_string(),
+            lines: Some(sender),
+            feeder: Some(feeder),
+            overflowing: false,
+            dropped: 0,
+        })
+    }
+
+    /// Send a line to the command, dropping it if the command doesn't read it in time or has
+    /// exited
+    pub fn write_line(&mut self, line: &str) {
+        let Some(lines) = &self.lines else {
+            self.dropped += 1;
+            return;
+        };
+
+        let deadline = Instant::now() + PIPE_TIMEOUT;
+        let mut line = line.to_string();
+        loop {
+            match lines.try_send(line) {
+                Ok(()) => {
+                    self.overflowing = false;
+                    return;
+                }
+                Err(TrySendError::Full(full)) if !self.overflowing && Instant::now() < deadline => {
+                    line = full;
+                    thread::sleep(Duration::from_millis(1));
+                }
+                Err(TrySendError::Full(_)) => {
+                    self.overflowing = true;
+                    self.dropped += 1;
+                    return;
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    self.lines = None;
+                    self.dropped += 1;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // Closing the channel closes the standard input of the command, and the feeder ends
+        // when the command exits
+        self.lines = None;
+        if let Some(feeder) = self.feeder.take() {
+            let _ = feeder.join();
+        }
+
+        if self.dropped > 0 {
+            eprintln!(
+                "{} lines were not written to `{}` because it was too slow or exited",
+                self.dropped, self.command
+            );
+        }
+    }
+}
+
+/// Files receiving the output of `--file`: `FILE`, and `FILE.<pid>` for the syscalls of every
+/// process with `--per-process`. With `--pipe-to`, or a `FILE` like `|command`, every line is
+/// sent to the command instead
+pub struct Output<'a> {
+    path: String,
+    args: &'a Args,
+    pipe: Option<Pipe>,
+    /// `FILE`, created at the first line written to it with `--per-process`
+    file: Option<OutputFile>,
+    processes: HashMap<Pid, OutputFile>,
+    header: Option<String>,
+}
+
+impl<'a> Output<'a> {
+    /// Open the output defined by `--file` or `--pipe-to`, if any
+    pub fn open(args: &'a Args) -> io::Result<Option<Self>> {
+        let command = args.pipe_to.as_deref().or(args
+            .file_to_print
+            .as_deref()
+            .and_then(|path| path.strip_prefix('|')));
+
+        if let Some(command) = command {
+            if args.max_size.is_some() || args.per_process {
+                return Err(io::Error::other(
+                    "`--max-size` and `--per-process` need a file, not a command",
+                ));
+            }
+
+            return Ok(Some(Self {
+                path: String::new(),
+                args,
+                pipe: Some(Pipe::spawn(command.trim())?),
+                file: None,
+                processes: HashMap::new(),
+                header: None,
+            }));
+        }
+
+        if args.max_size.is_some() && args.format == OutputFormat::ChromeTrace {
+            return Err(io::Error::other(
+                "`--max-size` can not split a Chrome trace, which is a single document",
+            ));
+        }
+
+        args.file_to_print
+            .as_deref()
+            .map(|path| Self::create(path, args))
+            .transpose()
+    }
+
+    /// Create the output to the file at `path`
+    fn create(path: &str, args: &'a Args) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_string(),
+            args,
+            pipe: None,
+            file: match args.per_process {
+                true => None,
+                false => Some(OutputFile::create(path, args)?),
+            },
+            processes: HashMap::new(),
+            header: None,
+        })
+    }
+
+    /// Write `header` at the start of every file
+    pub fn header(&mut self, header: &str) -> io::Result<()> {
+        self.header = Some(header.to_string());
+        if let Some(pipe) = &mut self.pipe {
+            pipe.write_line(header);
+        }
+        match &mut self.file {
+            Some(file) => file.header(header),
+            None => Ok(()),
+        }
+    }
+
+    /// Write a line which is not a syscall to `FILE`
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if let Some(pipe) = &mut self.pipe {
+            pipe.write_line(line);
+            return Ok(());
+        }
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(OutputFile::create(&self.path, self.args)?),
+        };
+
+        file.write_line(line)
+    }
+
+    /// Write the line of a syscall made by `pid` to its file. A Chrome trace is a single
+    /// document, so its syscalls are always written to `FILE`
+    pub fn write_syscall(&mut self, pid: Pid, line: &str) -> io::Result<()> {
+        if self.pipe.is_some()
+            || !self.args.per_process
+            || self.args.format == OutputFormat::ChromeTrace
+        {
+            return self.write_line(line);
+        }
+
+        let file = match self.processes.get_mut(&pid) {
+            Some(file) => file,
+            None => {
+                let mut file = OutputFile::create(&format!("{}.{pid}", self.path), self.args)?;
+                if let Some(header) = &self.header {
+                    file.header(header)?;
+                }
+                self.processes.entry(pid).or_insert(file)
+            }
+        };
+
+        file.write_line(line)
+    }
+}
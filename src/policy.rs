@@ -0,0 +1,166 @@
+use crate::{fds::FdTable, filter::Filter, registers::RegistersData};
+use clap::ValueEnum;
+use std::{fs, path::Path, str::FromStr};
+
+/// What to do when a syscall is outside the policy
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum PolicyAction {
+    /// Skip the syscall making it fail with `EPERM`
+    Deny,
+    /// Let the syscall run, only marking it as a violation
+    Log,
+    /// Kill the traced process
+    Kill,
+}
+
+/// Policy of the syscalls a process is allowed to make.
+///
+/// It is read from a file where every line is a rule and `#` starts a comment:
+/// - `allow EXPRESSION`: syscalls allowed, as a filter expression like `read,write,%memory`
+/// - `path PREFIX`: path prefix, like `/usr/lib`, allowed for the path name arguments
+///
+/// A syscall is allowed if it matches any `allow` rule and all its path name arguments start
+/// with any `path` prefix. If there are not `allow` rules every syscall is allowed, and if there
+/// are not `path` rules every path is allowed.
+#[derive(Clone, Debug)]
+pub struct Policy {
+    allow: Vec<Filter>,
+    paths: Vec<String>,
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        let mut allow = vec![];
+        let mut paths = vec![];
+
+        for (nro, line) in policy.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            match line.split_once(char::is_whitespace) {
+                Some(("allow", expression)) => allow.push(
+                    Filter::from_str(expression.trim())
+                        .map_err(|e| format!("line {}: {e}", nro + 1))?,
+                ),
+                Some(("path", prefix)) if prefix.trim().starts_with('/') => {
+                    paths.push(prefix.trim().to_string())
+                }
+                _ => return Err(format!("line {}: invalid rule `{line}`", nro + 1)),
+            }
+        }
+
+        Ok(Policy { allow, paths })
+    }
+}
+
+impl Policy {
+    /// Read a policy from the file at `path`
+    pub fn load(path: &str) -> Result<Self, String> {
+        let policy = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+
+        Policy::from_str(&policy).map_err(|e| format!("{path}: {e}"))
+    }
+
+    /// Check a syscall at its entry, returning the reason if it is outside the policy
+    pub fn check(&self, reg: &RegistersData, fds: &FdTable) -> Option<String> {
+        if !self.allow.is_empty() && !self.allow.iter().any(|filter| filter.matches(reg.name())) {
+            return Some("syscall not allowed".to_string());
+        }
+
+        if self.paths.is_empty() {
+            return None;
+        }
+
+        fds.path_args(reg)
+            .into_iter()
+            .find(|path| {
+                !self
+                    .paths
+                    .iter()
+                    .any(|prefix| Path::new(path).starts_with(prefix))
+            })
+            .map(|path| format!("path `{path}` not allowed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use nix::unistd::Pid;
+    use std::time::Duration;
+
+    const POLICY: &str = "# Reads its configuration
+allow read,close,%memory
+allow openat  # only under /etc
+path /etc
+";
+
+    /// `openat(AT_FDCWD, path, O_RDONLY)`
+    fn openat(path: &str) -> RegistersData {
+        RegistersData::load(
+            Pid::from_raw(1),
+            Local::now(),
+            (Duration::ZERO, Duration::ZERO),
+            [257, -100i64 as u64, 0x1000, 0, 0, 0, 0, 3],
+            vec![(1, path.to_string())],
+        )
+    }
+
+    /// Syscall `nro` without arguments
+    fn syscall(nro: u64) -> RegistersData {
+        RegistersData::load(
+            Pid::from_raw(1),
+            Local::now(),
+            (Duration::ZERO, Duration::ZERO),
+            [nro, 0, 0, 0, 0, 0, 0, 0],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn check() {
+        let policy = Policy::from_str(POLICY).unwrap();
+        let fds = FdTable::offline();
+
+        assert_eq!(policy.check(&openat("/etc/hostname"), &fds), None);
+        assert_eq!(
+            policy.check(&openat("/etcetera"), &fds).as_deref(),
+            Some("path `/etcetera` not allowed")
+        );
+        assert_eq!(policy.check(&syscall(0), &fds), None);
+        assert_eq!(
+            policy.check(&syscall(39), &fds).as_deref(),
+            Some("syscall not allowed")
+        );
+    }
+
+    #[test]
+    fn empty_policy() {
+        let policy = Policy::from_str("# Nothing\n\n").unwrap();
+        let fds = FdTable::offline();
+
+        assert_eq!(policy.check(&openat("/root/.ssh/id_rsa"), &fds), None);
+        assert_eq!(policy.check(&syscall(39), &fds), None);
+    }
+
+    #[test]
+    fn invalid_policies() {
+        let error = |policy: &str| Policy::from_str(policy).unwrap_err();
+
+        assert_eq!(
+            error("allow read\ndeny write"),
+            "line 2: invalid rule `deny write`"
+        );
+        assert_eq!(error("path etc"), "line 1: invalid rule `path etc`");
+        assert_eq!(error("allow"), "line 1: invalid rule `allow`");
+        assert_eq!(
+            error("\nallow nosuchcall"),
+            "line 2: unknown syscall `nosuchcall`"
+        );
+    }
+}
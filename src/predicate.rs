@@ -0,0 +1,308 @@
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+use crate::arch::linux::x86_64::*;
+use crate::registers::{argument_name, RegistersData};
+use glob::Pattern;
+use std::str::FromStr;
+
+/// Operators which can be used in a comparison, sorted to match the longest one first
+const OPERATORS: [&str; 7] = ["==", "!=", "<=", ">=", "<", ">", "~"];
+
+/// Parse an integer value which can be hexadecimal (`0x10`), negative and with a binary size
+/// suffix (`K`, `KiB`, `M`, `MiB`, `G`, `GiB`)
+pub fn parse_number(value: &str) -> Option<i64> {
+    let (value, multiplier) = [("K", 1 << 10), ("M", 1 << 20), ("G", 1 << 30)]
+        .iter()
+        .find_map(|(suffix, multiplier)| {
+            value
+                .strip_suffix("iB")
+                .unwrap_or(value)
+                .strip_suffix(suffix)
+                .map(|value| (value, *multiplier))
+        })
+        .unwrap_or((value, 1));
+
+    let (value, sign) = match value.strip_prefix('-') {
+        Some(value) => (value, -1),
+        None => (value, 1),
+    };
+
+    let number = match value.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => value.parse::<i64>().ok()?,
+    };
+
+    number.checked_mul(sign)?.checked_mul(multiplier)
+}
+
+/// Value of the right side of a comparison
+#[derive(Clone, Debug)]
+enum Value {
+    Number(i64),
+    String(String),
+    Glob(Pattern),
+}
+
+/// A comparison like `fd == 2` between a field of the syscall and a value
+#[derive(Clone, Debug)]
+struct Comparison {
+    /// Name of an argument, eg: `fd`, `ret` for the return value or `duration` in nanoseconds
+    field: String,
+    operator: &'static str,
+    value: Value,
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(comparison: &str) -> Result<Self, Self::Err> {
+        let (field, operator, value) = OPERATORS
+            .iter()
+            .find_map(|operator| {
+                comparison
+                    .split_once(operator)
+                    .map(|(field, value)| (field.trim(), *operator, value.trim()))
+            })
+            .ok_or(format!("missing operator in `{comparison}`"))?;
+
+        if field.is_empty() {
+            return Err(format!("missing field in `{comparison}`"));
+        }
+
+        let unquoted = value.trim_matches('"');
+        if field == "duration" {
+            let duration = humantime::parse_duration(unquoted)
+                .map_err(|e| format!("invalid duration `{value}`: {e}"))?;
+            return Ok(Comparison {
+                field: field.to_string(),
+                operator,
+                value: Value::Number(duration.as_nanos() as i64),
+            });
+        }
+
+        let value = match (operator, parse_number(value)) {
+            ("~", _) => Value::Glob(
+                Pattern::new(unquoted).map_err(|e| format!("invalid glob `{value}`: {e}"))?,
+            ),
+            (_, Some(number)) => Value::Number(number),
+            ("==" | "!=", None) => Value::String(unquoted.to_string()),
+            (_, None) => return Err(format!("`{value}` is not a number")),
+        };
+
+        Ok(Comparison {
+            field: field.to_string(),
+            operator,
+            value,
+        })
+    }
+}
+
+impl Comparison {
+    fn matches(&self, reg: &RegistersData) -> bool {
+        let (argument, raw) = if self.field == "ret" {
+            ("long ret", reg.retval() as u64)
+        } else if self.field == "duration" {
+            ("u64 duration", reg.duration().as_nanos() as u64)
+        } else {
+            match reg
+                .args()
+                .into_iter()
+                .find(|(argument, _)| argument_name(argument) == self.field)
+            {
+                Some(arg) => arg,
+                None => return false,
+            }
+        };
+
+        match &self.value {
+            Value::Number(number) => {
+                let value = if argument.starts_with("int ") {
+                    raw as i32 as i64
+                } else {
+                    raw as i64
+                };
+
+                match self.operator {
+                    "==" => value == *number,
+                    "!=" => value != *number,
+                    "<=" => value <= *number,
+                    ">=" => value >= *number,
+                    "<" => value < *number,
+                    ">" => value > *number,
+                    _ => false,
+                }
+            }
+            Value::String(string) => {
+                let value = reg.string(raw);
+                (self.operator == "==") == (value == *string)
+            }
+            Value::Glob(pattern) => pattern.matches(&reg.string(raw)),
+        }
+    }
+}
+
+/// Predicate over the arguments and the return value of a syscall.
+///
+/// It is formatted like `[syscall:]field op value [&& field op value]...`, where `field` is the
+/// name of an argument as shown in the output (eg: `fd`, `length`, `filename`), `ret` for the
+/// return value or `duration` for the time spent in the syscall (eg: `10ms`), `op` is one of
+/// `==`, `!=`, `<`, `<=`, `>`, `>=` and `~` for glob matching of a string argument. Numbers can
+/// be hexadecimal and have a `K`, `M` or `G` binary suffix.
+/// Eg: `write:fd==2`, `mmap:length > 1MiB`, `openat:filename ~ /etc/*`.
+#[derive(Clone, Debug)]
+pub struct Predicate {
+    syscall: Option<String>,
+    comparisons: Vec<Comparison>,
+}
+
+impl FromStr for Predicate {
+    type Err = String;
+
+    fn from_str(predicate: &str) -> Result<Self, Self::Err> {
+        let (syscall, expression) = match predicate.split_once(':') {
+            Some((name, expression))
+                if name
+                    .trim()
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                (Some(name.trim().to_string()), expression)
+            }
+            _ => (None, predicate),
+        };
+
+        let comparisons = expression
+            .split("&&")
+            .map(Comparison::from_str)
+            .collect::<Result<Vec<Comparison>, String>>()?;
+
+        if let Some(name) = &syscall {
+            let nro = syscall_number(name).ok_or(format!("unknown syscall `{name}`"))?;
+
+            for comparison in &comparisons {
+                let found = [rdi(nro), rsi(nro), rdx(nro), r10(nro), r8(nro), r9(nro)]
+                    .iter()
+                    .any(|argument| argument_name(argument) == comparison.field);
+                if !["ret", "duration"].contains(&comparison.field.as_str()) && !found {
+                    return Err(format!(
+                        "`{name}` does not have an argument `{}`",
+                        comparison.field
+                    ));
+                }
+            }
+        }
+
+        Ok(Predicate {
+            syscall,
+            comparisons,
+        })
+    }
+}
+
+impl Predicate {
+    /// Check if a line, taken at the exit of its syscall, satisfies every comparison
+    pub fn matches(&self, reg: &RegistersData) -> bool {
+        if let Some(name) = &self.syscall {
+            if name != reg.name() {
+                return false;
+            }
+        }
+
+        self.comparisons
+            .iter()
+            .all(|comparison| comparison.matches(reg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+    use nix::unistd::Pid;
+    use std::time::Duration;
+
+    /// `openat(AT_FDCWD, "/etc/hostname", O_RDONLY|O_CLOEXEC)` returning `retval` after 2ms
+    fn openat(retval: i64) -> RegistersData {
+        RegistersData::load(
+            Pid::from_raw(1),
+            Local::now(),
+            (Duration::ZERO, Duration::from_millis(2)),
+            [
+                257,
+                -100i64 as u64,
+                0x1000,
+                0o2000000,
+                0,
+                0,
+                0,
+                retval as u64,
+            ],
+            vec![(1, "/etc/hostname".to_string())],
+        )
+    }
+
+    fn matches(predicate: &str, reg: &RegistersData) -> bool {
+        predicate.parse::<Predicate>().unwrap().matches(reg)
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("42"), Some(42));
+        assert_eq!(parse_number("-1"), Some(-1));
+        assert_eq!(parse_number("0x10"), Some(16));
+        assert_eq!(parse_number("4K"), Some(4096));
+        assert_eq!(parse_number("1MiB"), Some(1 << 20));
+        assert_eq!(parse_number("-2G"), Some(-2 << 30));
+        assert_eq!(parse_number(""), None);
+        assert_eq!(parse_number("ten"), None);
+        assert_eq!(parse_number("1T"), None);
+        // Overflows are rejected instead of wrapping
+        assert_eq!(parse_number("9223372036854775807K"), None);
+        assert_eq!(parse_number("99999999999999999999"), None);
+    }
+
+    #[test]
+    fn comparisons() {
+        let reg = openat(3);
+
+        assert!(matches("ret == 3", &reg));
+        assert!(matches("ret>=3", &reg));
+        assert!(!matches("ret < 3", &reg));
+        assert!(matches("dfd == -100", &reg));
+        assert!(matches("flags == 0x80000", &reg));
+        assert!(matches("openat:filename == \"/etc/hostname\"", &reg));
+        assert!(matches("filename != /etc/passwd", &reg));
+        assert!(matches("openat:filename ~ /etc/*", &reg));
+        assert!(!matches("filename ~ /usr/*", &reg));
+        assert!(matches("duration > 1ms && duration < 3ms", &reg));
+        assert!(matches("openat:ret > 0 && filename ~ *host*", &reg));
+        assert!(!matches("openat:ret > 0 && filename ~ *passwd", &reg));
+        assert!(!matches("openat:ret < 0", &openat(3)));
+        assert!(matches("openat:ret < 0", &openat(-2)));
+    }
+
+    #[test]
+    fn other_syscalls_and_arguments() {
+        let reg = openat(3);
+
+        assert!(!matches("close:ret == 3", &reg));
+        // A missing argument never matches
+        assert!(!matches("count > 0", &reg));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |predicate: &str| predicate.parse::<Predicate>().unwrap_err();
+
+        assert_eq!(error("fd"), "missing operator in `fd`");
+        assert_eq!(error("== 2"), "missing field in `== 2`");
+        assert_eq!(error("fd > two"), "`two` is not a number");
+        assert_eq!(error("nosuchcall:fd == 1"), "unknown syscall `nosuchcall`");
+        assert_eq!(
+            error("close:count == 1"),
+            "`close` does not have an argument `count`"
+        );
+        assert!(error("duration > soon").starts_with("invalid duration `soon`"));
+        assert!(error("filename ~ [").starts_with("invalid glob `[`"));
+        assert!(error("fd == 1 && ret").starts_with("missing operator"));
+    }
+}
@@ -0,0 +1,506 @@
+use crate::{
+    event::Value,
+    registers::RegistersData,
+    trace::{ProcessEvent, ProcessEventKind},
+};
+use anyhow::bail;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use chrono::{Local, TimeZone};
+use nix::{sys::signal::Signal, unistd::Pid};
+use std::{
+    fs::File,
+    io::{BufWriter, Cursor, ErrorKind, Read, Write},
+    time::Duration,
+};
+
+/// Magic bytes at the start of a recording
+pub const MAGIC: &[u8; 8] = b"SIGMATRC";
+
+/// Version of the schema of a recording, incremented on every incompatible change
+pub const VERSION: u16 = 1;
+
+/// ELF machine of the recorded registers, `EM_X86_64`
+pub const MACHINE: u16 = 62;
+
+/// Kind of the record of a syscall
+pub const RECORD_SYSCALL: u8 = 1;
+
+/// Kind of the record of a process event
+pub const RECORD_PROCESS: u8 = 2;
+
+/// Flag of a syscall record set if its result has been injected by the tracer
+pub const FLAG_INJECTED: u8 = 1;
+
+/// Flag of a syscall record set if the syscall doesn't return, like `exit_group`
+pub const FLAG_UNFINISHED: u8 = 2;
+
+/// Process events, as the `event` field of a process record
+pub const PROCESS_SIGNAL: u8 = 0;
+pub const PROCESS_EXITED: u8 = 1;
+pub const PROCESS_KILLED: u8 = 2;
+
+/// Writer of a binary recording of a trace, used by `--record` and read by `Reader`.
+///
+/// All the integers are little endian and the strings are UTF-8 prefixed by their length. A
+/// recording starts with a header:
+///
+/// | field   | type     | value                         |
+/// |---------|----------|-------------------------------|
+/// | magic   | [u8; 8]  | `SIGMATRC`                    |
+/// | version | u16      | `VERSION`                     |
+/// | machine | u16      | ELF machine of the registers  |
+///
+/// followed by records until the end of the file, each one as `kind: u8`, `length: u32` and
+/// `length` bytes of payload. Readers must skip the records with an unknown kind and the bytes
+/// of a payload after the known fields, so new records and fields can be appended without
+/// breaking them.
+///
+/// Payload of a syscall record (`RECORD_SYSCALL`), taken at the exit of the syscall:
+///
+/// | field     | type                | description                                        |
+/// |-----------|---------------------|----------------------------------------------------|
+/// | tid       | i32                 | thread which made the syscall                      |
+/// | timestamp | i64                 | wall clock time, nanoseconds since the epoch       |
+/// | entry     | u64                 | entry time from a monotonic clock, nanoseconds     |
+/// | exit      | u64                 | exit time from a monotonic clock, nanoseconds      |
+/// | registers | [u64; 8]            | `orig_rax`, `rdi`, `rsi`, `rdx`, `r10`, `r8`, `r9`, `rax` |
+/// | flags     | u8                  | `FLAG_INJECTED`, `FLAG_UNFINISHED`                 |
+/// | violation | u16 + bytes         | reason of the policy violation, empty if none      |
+/// | tampered  | u8 + [u8 + bytes, u64, u64] | values changed by the tracer as (argument, original, modified) |
+/// | memory    | u8 + [u8, u32 + bytes] | strings read from the memory, as (argument index, bytes) |
+/// | process   | i32                 | process of the thread, `tid` if missing            |
+///
+/// Payload of a process record (`RECORD_PROCESS`):
+///
+/// | field     | type | description                                                  |
+/// |-----------|------|--------------------------------------------------------------|
+/// | tid       | i32  | thread of the event                                          |
+/// | timestamp | i64  | wall clock time, nanoseconds since the epoch                 |
+/// | time      | u64  | time from a monotonic clock, nanoseconds                     |
+/// | event     | u8   | `PROCESS_SIGNAL`, `PROCESS_EXITED` or `PROCESS_KILLED`       |
+/// | value     | i32  | number of the signal, or the exit code                       |
+/// | process   | i32  | process of the thread, `tid` if missing                      |
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+/// Write a string prefixed by its length as an `u8` or an `u16`, truncating it if needed
+fn write_string(payload: &mut Vec<u8>, string: &str, wide: bool) -> std::io::Result<()> {
+    let bytes = string.as_bytes();
+    if wide {
+        let bytes = &bytes[..bytes.len().min(u16::MAX as usize)];
+        payload.write_u16::<LittleEndian>(bytes.len() as u16)?;
+        payload.write_all(bytes)
+    } else {
+        let bytes = &bytes[..bytes.len().min(u8::MAX as usize)];
+        payload.write_u8(bytes.len() as u8)?;
+        payload.write_all(bytes)
+    }
+}
+
+impl Recorder {
+    /// Create the recording at `path` writing its header
+    pub fn create(path: &str) -> anyhow::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_u16::<LittleEndian>(VERSION)?;
+        writer.write_u16::<LittleEndian>(MACHINE)?;
+
+        Ok(Self { writer })
+    }
+
+    fn write(&mut self, kind: u8, payload: &[u8]) -> anyhow::Result<()> {
+        self.writer.write_u8(kind)?;
+        self.writer
+            .write_u32::<LittleEndian>(payload.len() as u32)?;
+        self.writer.write_all(payload)?;
+
+        Ok(())
+    }
+
+    /// Append a syscall, taken at its exit
+    pub fn syscall(&mut self, reg: &RegistersData) -> anyhow::Result<()> {
+        let event = reg.event(false);
+        let mut payload = vec![];
+
+        payload.write_i32::<LittleEndian>(event.tid)?;
+        payload.write_i64::<LittleEndian>(reg.timestamp().timestamp_nanos_opt().unwrap_or(0))?;
+        payload.write_u64::<LittleEndian>(event.entry_ns)?;
+        payload.write_u64::<LittleEndian>(event.exit_ns)?;
+        for register in [
+            event.registers.orig_rax,
+            event.registers.rdi,
+            event.registers.rsi,
+            event.registers.rdx,
+            event.registers.r10,
+            event.registers.r8,
+            event.registers.r9,
+            event.registers.rax,
+        ] {
+            payload.write_u64::<LittleEndian>(register)?;
+        }
+        let mut flags = 0;
+        if event.injected {
+            flags |= FLAG_INJECTED;
+        }
+        if event.unfinished {
+            flags |= FLAG_UNFINISHED;
+        }
+        payload.write_u8(flags)?;
+        write_string(&mut payload, &event.violation.unwrap_or_default(), true)?;
+
+        payload.write_u8(event.tampered.len().min(u8::MAX as usize) as u8)?;
+        for tampered in event.tampered.iter().take(u8::MAX as usize) {
+            write_string(&mut payload, &tampered.argument, false)?;
+            payload.write_u64::<LittleEndian>(tampered.original)?;
+            payload.write_u64::<LittleEndian>(tampered.modified)?;
+        }
+
+        let memory: Vec<(usize, &String)> = event
+            .args
+            .iter()
+            .enumerate()
+            .filter_map(|(index, argument)| match &argument.value {
+                Value::String(string) => Some((index, string)),
+                _ => None,
+            })
+            .collect();
+        payload.write_u8(memory.len() as u8)?;
+        for (index, string) in memory {
+            payload.write_u8(index as u8)?;
+            payload.write_u32::<LittleEndian>(string.len() as u32)?;
+            payload.write_all(string.as_bytes())?;
+        }
+        payload.write_i32::<LittleEndian>(event.pid)?;
+
+        self.write(RECORD_SYSCALL, &payload)
+    }
+
+    /// Append an event of a process, flushing the recording when the process ends
+    pub fn process(&mut self, event: &ProcessEvent) -> anyhow::Result<()> {
+        let mut payload = vec![];
+        let (kind, value) = match event.kind {
+            ProcessEventKind::Signal(signal) => (PROCESS_SIGNAL, signal as i32),
+            ProcessEventKind::Exited(code) => (PROCESS_EXITED, code),
+            ProcessEventKind::Killed(signal) => (PROCESS_KILLED, signal as i32),
+        };
+
+        payload.write_i32::<LittleEndian>(event.pid.as_raw())?;
+        payload.write_i64::<LittleEndian>(event.timestamp.timestamp_nanos_opt().unwrap_or(0))?;
+        payload.write_u64::<LittleEndian>(event.time.as_nanos() as u64)?;
+        payload.write_u8(kind)?;
+        payload.write_i32::<LittleEndian>(value)?;
+        payload.write_i32::<LittleEndian>(event.process.as_raw())?;
+        self.write(RECORD_PROCESS, &payload)?;
+
+        if kind != PROCESS_SIGNAL {
+            self.writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Record read from a recording
+pub enum Record {
+    Syscall(Box<RegistersData>),
+    Process(ProcessEvent),
+}
+
+impl Record {
+    /// Returns the process of the record
+    pub fn pid(&self) -> Pid {
+        match self {
+            Record::Syscall(reg) => reg.pid(),
+            Record::Process(event) => event.pid,
+        }
+    }
+}
+
+/// Streaming reader of a recording written by `Recorder`
+pub struct Reader<R: Read> {
+    reader: R,
+}
+
+/// Read a string prefixed by its length as an `u8` or an `u16`
+fn read_string(payload: &mut Cursor<Vec<u8>>, wide: bool) -> std::io::Result<String> {
+    let length = if wide {
+        payload.read_u16::<LittleEndian>()? as usize
+    } else {
+        payload.read_u8()? as usize
+    };
+    let mut bytes = vec![0; length];
+    payload.read_exact(&mut bytes)?;
+
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+impl<R: Read> Reader<R> {
+    /// Check the header of a recording, which must have a known version and the registers of
+    /// this architecture
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("not a sigma-trace recording");
+        }
+
+        let version = reader.read_u16::<LittleEndian>()?;
+        if version > VERSION {
+            bail!("unsupported recording version {version}, the latest one is {VERSION}");
+        }
+
+        let machine = reader.read_u16::<LittleEndian>()?;
+        if machine != MACHINE {
+            bail!("the recording has been made on another architecture (ELF machine {machine})");
+        }
+
+        Ok(Self { reader })
+    }
+
+    /// Returns the next record skipping the unknown ones, or `None` at the end of the
+    /// recording. A record truncated by a crash of the tracer ends the recording
+    pub fn next(&mut self) -> anyhow::Result<Option<Record>> {
+        loop {
+            let mut header = [0; 5];
+            match self.reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+
+            let kind = header[0];
+            let length = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+            let mut payload = vec![0; length as usize];
+            match self.reader.read_exact(&mut payload) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+
+            let mut payload = Cursor::new(payload);
+            let record = match kind {
+                RECORD_SYSCALL => Some(Record::Syscall(Box::new(Self::syscall(&mut payload)?))),
+                RECORD_PROCESS => Self::process(&mut payload)?.map(Record::Process),
+                _ => None,
+            };
+
+            if record.is_some() {
+                return Ok(record);
+            }
+        }
+    }
+
+    fn syscall(payload: &mut Cursor<Vec<u8>>) -> anyhow::Result<RegistersData> {
+        let pid = Pid::from_raw(payload.read_i32::<LittleEndian>()?);
+        let timestamp = Local.timestamp_nanos(payload.read_i64::<LittleEndian>()?);
+        let entry = Duration::from_nanos(payload.read_u64::<LittleEndian>()?);
+        let exit = Duration::from_nanos(payload.read_u64::<LittleEndian>()?);
+        let mut registers = [0; 8];
+        payload.read_u64_into::<LittleEndian>(&mut registers)?;
+        let flags = payload.read_u8()?;
+        let violation = read_string(payload, true)?;
+
+        let mut tampered = vec![];
+        for _ in 0..payload.read_u8()? {
+            tampered.push((
+                read_string(payload, false)?,
+                payload.read_u64::<LittleEndian>()?,
+                payload.read_u64::<LittleEndian>()?,
+            ));
+        }
+
+        let mut strings = vec![];
+        for _ in 0..payload.read_u8()? {
+            let index = payload.read_u8()? as usize;
+            let mut bytes = vec![0; payload.read_u32::<LittleEndian>()? as usize];
+            payload.read_exact(&mut bytes)?;
+            strings.push((index, String::from_utf8_lossy(&bytes).to_string()));
+        }
+
+        let mut reg = RegistersData::load(pid, timestamp, (entry, exit), registers, strings);
+        if flags & FLAG_INJECTED != 0 {
+            reg.set_injected();
+        }
+        if flags & FLAG_UNFINISHED != 0 {
+            reg.set_unfinished();
+        }
+        for (argument, original, modified) in tampered {
+            reg.set_tampered(argument, original, modified);
+        }
+        if !violation.is_empty() {
+            reg.set_violation(violation);
+        }
+        // Recordings made before the process was recorded only have the thread
+        if let Ok(process) = payload.read_i32::<LittleEndian>() {
+            reg.set_process(Pid::from_raw(process));
+        }
+
+        Ok(reg)
+    }
+
+    fn process(payload: &mut Cursor<Vec<u8>>) -> anyhow::Result<Option<ProcessEvent>> {
+        let pid = Pid::from_raw(payload.read_i32::<LittleEndian>()?);
+        let timestamp = Local.timestamp_nanos(payload.read_i64::<LittleEndian>()?);
+        let time = Duration::from_nanos(payload.read_u64::<LittleEndian>()?);
+        let event = payload.read_u8()?;
+        let value = payload.read_i32::<LittleEndian>()?;
+        let process = payload
+            .read_i32::<LittleEndian>()
+            .map_or(pid, Pid::from_raw);
+
+        let kind = match (event, Signal::try_from(value)) {
+            (PROCESS_SIGNAL, Ok(signal)) => ProcessEventKind::Signal(signal),
+            (PROCESS_EXITED, _) => ProcessEventKind::Exited(value),
+            (PROCESS_KILLED, Ok(signal)) => ProcessEventKind::Killed(signal),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(ProcessEvent {
+            pid,
+            process,
+            timestamp,
+            time,
+            kind,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    /// Path of a recording in the temporary directory, unique to the test
+    fn path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("sigma-trace-{}-{name}", process::id()))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// `openat(AT_FDCWD, "/etc/hostname", O_RDONLY)` returning 3, made by the thread 11 of the
+    /// process 10
+    fn openat() -> RegistersData {
+        let mut reg = RegistersData::load(
+            Pid::from_raw(11),
+            Local.timestamp_nanos(1_700_000_000_123_456_789),
+            (Duration::from_nanos(1_000), Duration::from_nanos(3_500)),
+            [257, -100i64 as u64, 0x7fff_0000, 0, 0, 0, 0, 3],
+            vec![(1, "/etc/hostname".to_string())],
+        );
+        reg.set_process(Pid::from_raw(10));
+        reg
+    }
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let path = path("round-trip");
+        let mut recorder = Recorder::create(&path)?;
+
+        let mut injected = openat();
+        injected.set_injected();
+        injected.set_tampered("flags".to_string(), 0, 1);
+        injected.set_violation("openat of /etc/hostname".to_string());
+        let mut exit_group = RegistersData::load(
+            Pid::from_raw(10),
+            Local.timestamp_nanos(1_700_000_001_000_000_000),
+            (Duration::from_nanos(4_000), Duration::from_nanos(4_000)),
+            [231, 0, 0, 0, 0, 0, 0, -38i64 as u64],
+            vec![],
+        );
+        exit_group.set_unfinished();
+        let exited = ProcessEvent {
+            pid: Pid::from_raw(10),
+            process: Pid::from_raw(10),
+            timestamp: Local.timestamp_nanos(1_700_000_001_000_000_500),
+            time: Duration::from_nanos(4_500),
+            kind: ProcessEventKind::Exited(0),
+        };
+
+        recorder.syscall(&openat())?;
+        recorder.syscall(&injected)?;
+        recorder.syscall(&exit_group)?;
+        recorder.process(&exited)?;
+        drop(recorder);
+
+        let mut reader = Reader::new(File::open(&path)?)?;
+        let mut records = vec![];
+        while let Some(record) = reader.next()? {
+            records.push(record);
+        }
+        fs::remove_file(&path)?;
+
+        let [Record::Syscall(first), Record::Syscall(second), Record::Syscall(third), Record::Process(event)] =
+            records.as_slice()
+        else {
+            panic!("unexpected records");
+        };
+
+        let (expected, loaded) = (openat().event(false), first.event(false));
+        assert_eq!(loaded.pid, 10);
+        assert_eq!(loaded.tid, 11);
+        assert_eq!(loaded.timestamp, expected.timestamp);
+        assert_eq!((loaded.entry_ns, loaded.exit_ns), (1_000, 3_500));
+        assert_eq!(loaded.name, "openat");
+        assert_eq!(loaded.retval, 3);
+        assert!(matches!(&loaded.args[1].value, Value::String(path) if path == "/etc/hostname"));
+        assert!(!loaded.injected && loaded.tampered.is_empty() && loaded.violation.is_none());
+
+        let loaded = second.event(false);
+        assert!(loaded.injected);
+        assert_eq!(loaded.tampered.len(), 1);
+        assert_eq!(
+            (
+                loaded.tampered[0].argument.as_str(),
+                loaded.tampered[0].modified
+            ),
+            ("flags", 1)
+        );
+        assert_eq!(loaded.violation.as_deref(), Some("openat of /etc/hostname"));
+
+        assert!(third.unfinished());
+        assert_eq!(third.name(), "exit_group");
+        assert_eq!(third.errno(), None);
+
+        assert_eq!((event.pid, event.process), (exited.pid, exited.process));
+        assert_eq!(event.timestamp, exited.timestamp);
+        assert_eq!(event.time, exited.time);
+        assert!(matches!(event.kind, ProcessEventKind::Exited(0)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_and_truncated_records() -> anyhow::Result<()> {
+        let path = path("unknown");
+        let mut recorder = Recorder::create(&path)?;
+        recorder.write(0xff, &[1, 2, 3])?;
+        recorder.syscall(&openat())?;
+        // A record cut by a crash of the tracer
+        recorder
+            .writer
+            .write_all(&[RECORD_SYSCALL, 100, 0, 0, 0, 1])?;
+        drop(recorder);
+
+        let mut reader = Reader::new(File::open(&path)?)?;
+        let first = reader.next()?;
+        let end = reader.next()?;
+        fs::remove_file(&path)?;
+
+        assert!(matches!(first, Some(Record::Syscall(reg)) if reg.name() == "openat"));
+        assert!(end.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_header() {
+        let mut header = MAGIC.to_vec();
+        header.extend((VERSION + 1).to_le_bytes());
+        header.extend(MACHINE.to_le_bytes());
+        assert!(Reader::new(header.as_slice()).is_err());
+
+        assert!(Reader::new(&b"NOTATRACE\0\0\0"[..]).is_err());
+    }
+}
@@ -1,16 +1,28 @@
-use chrono::{DateTime, Local};
-use nix::{libc::user_regs_struct, unistd::Pid};
-use owo_colors::OwoColorize;
+use crate::cli::Args;
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use nix::{
+    errno::Errno,
+    libc::user_regs_struct,
+    time::{clock_gettime, ClockId},
+    unistd::Pid,
+};
+use owo_colors::{OwoColorize, Style as Paint};
 use ratatui::{
     prelude::{Line, Span, Style},
     style::{Color, Modifier},
 };
+use std::{env, time::Duration};
 
 #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
 use crate::arch::linux::x86_64::*;
 #[cfg(not(all(target_arch = "x86_64", target_os = "linux")))]
 use crate::arch::syscall_name;
-use crate::trace::read_memory;
+use crate::{
+    event::{Argument, Event, OutputFormat, Registers, Tampered, Value},
+    stack::Frame,
+    trace::{quote_string, read_bytes, read_string},
+};
 
 #[derive(Clone, Debug)]
 /// Structure use to monitor what a register has for (argument: value)
@@ -18,23 +30,110 @@ struct RegisterOutput {
     /// Value for a register, by default is a number which could be a real value or a memory
     /// address
     value: String,
+    /// Raw value read from the register
+    raw: u64,
     /// Argument for a register, eg: "const char *buf"
     argument: &'static str,
+    /// String pointed by the register, captured when the syscall has been recorded
+    captured: Option<String>,
 }
 
 impl RegisterOutput {
     fn new(address: u64, argument: &'static str) -> Self {
         Self {
             value: address.to_string(),
+            raw: address,
             argument,
+            captured: None,
+        }
+    }
+
+    /// Returns the string pointed by the register, captured or read from the memory of `pid`
+    fn string(&self, pid: Pid) -> String {
+        self.captured
+            .clone()
+            .unwrap_or_else(|| read_string(pid, self.raw))
+    }
+}
+
+/// Returns the name of the variable for an argument, eg: "filename" for "const char *filename"
+/// or "addr" for "void addr[.length]"
+pub fn argument_name(argument: &str) -> &str {
+    let name = argument
+        .rsplit(|c: char| c.is_whitespace() || c == '*')
+        .next()
+        .unwrap_or_default();
+
+    name.split('[').next().unwrap_or_default()
+}
+
+/// Returns if an argument points to a string, like "const char *filename", but not to an array
+/// of strings, like "const char *const *argv"
+pub fn is_string(argument: &str) -> bool {
+    (argument.starts_with("const char *") || argument.starts_with("char *"))
+        && argument.matches('*').count() == 1
+}
+
+/// Format of the timestamp shown before every line
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum TimestampFormat {
+    /// No timestamp
+    None,
+    /// Wall clock time, like `12:34:56`
+    Time,
+    /// Wall clock time with microseconds, like `12:34:56.123456`
+    Us,
+    /// Seconds since the epoch with microseconds, like `1700000000.123456`
+    Unix,
+    /// Seconds since the start of the tracing
+    Relative,
+    /// Seconds since the previous shown syscall
+    Delta,
+    /// ISO 8601 / RFC 3339 date & time, like `2023-11-14T12:34:56.123456789+01:00`
+    Rfc3339,
+}
+
+/// When to color the output
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color only the standard output and the standard error, if they are terminals and
+    /// `NO_COLOR` is not set
+    Auto,
+    /// Always color, also the file output
+    Always,
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns if a stream has to be colored, where `terminal` tells if it is a terminal. Files
+    /// are only colored with `Always`
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
         }
     }
 }
 
+/// Returns the current time of the monotonic clock
+pub fn monotonic() -> Duration {
+    clock_gettime(ClockId::CLOCK_MONOTONIC)
+        .map(Duration::from)
+        .unwrap_or_default()
+}
+
 /// Struct used to manipulate registers data from https://docs.rs/libc/0.2.147/libc/struct.user_regs_struct.html
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RegistersData {
     timestamp: DateTime<Local>,
+    /// Thread which made the syscall
+    pid: Pid,
+    /// Process of the thread, its thread group
+    process: Pid,
     orig_rax: u64,
     rdi: RegisterOutput,
     rsi: RegisterOutput,
@@ -43,36 +142,278 @@ pub struct RegistersData {
     r8: RegisterOutput,
     r9: RegisterOutput,
     rax: u64,
+    /// If the result of the syscall has been injected by the tracer
+    injected: bool,
+    /// The syscall doesn't return, like `exit_group`, so it has been taken at its entry
+    /// without a result
+    unfinished: bool,
+    /// Values changed by the tracer as (argument, original value, modified value)
+    tampered: Vec<(String, u64, u64)>,
+    /// Reason why the syscall is outside the policy
+    violation: Option<String>,
+    /// Time of the entry of the syscall, from a monotonic clock
+    entry: Duration,
+    /// Time of the exit of the syscall, from a monotonic clock
+    exit: Duration,
+    /// Time since the start of the tracing, at the entry of the syscall
+    relative: Duration,
+    /// Time since the entry of the previous shown syscall, at the entry of the syscall
+    delta: Duration,
+    /// Stack of the process at the entry of the syscall, with `--stack`
+    stack: Vec<Frame>,
 }
 
 impl RegistersData {
-    /// Create new `RegistersData` from an `user_regs_struct`'C structure
-    pub fn new(registers: user_regs_struct) -> RegistersData {
+    /// Create new `RegistersData` from an `user_regs_struct`'C structure of the process `pid`
+    pub fn new(pid: Pid, registers: user_regs_struct) -> RegistersData {
+        RegistersData::from_raw(
+            pid,
+            [
+                registers.orig_rax,
+                registers.rdi,
+                registers.rsi,
+                registers.rdx,
+                registers.r10,
+                registers.r8,
+                registers.r9,
+                registers.rax,
+            ],
+        )
+    }
+
+    /// Create new `RegistersData` from the values of `orig_rax`, `rdi`, `rsi`, `rdx`, `r10`,
+    /// `r8`, `r9` and `rax`
+    fn from_raw(pid: Pid, registers: [u64; 8]) -> RegistersData {
+        let [orig_rax, rdi_value, rsi_value, rdx_value, r10_value, r8_value, r9_value, rax] =
+            registers;
         let (rdi, rsi, rdx, r10, r8, r9) = (
-            RegisterOutput::new(registers.rdi, rdi(registers.orig_rax)),
-            RegisterOutput::new(registers.rsi, rsi(registers.orig_rax)),
-            RegisterOutput::new(registers.rdx, rdx(registers.orig_rax)),
-            RegisterOutput::new(registers.r10, r10(registers.orig_rax)),
-            RegisterOutput::new(registers.r8, r8(registers.orig_rax)),
-            RegisterOutput::new(registers.r9, r9(registers.orig_rax)),
+            RegisterOutput::new(rdi_value, rdi(orig_rax)),
+            RegisterOutput::new(rsi_value, rsi(orig_rax)),
+            RegisterOutput::new(rdx_value, rdx(orig_rax)),
+            RegisterOutput::new(r10_value, r10(orig_rax)),
+            RegisterOutput::new(r8_value, r8(orig_rax)),
+            RegisterOutput::new(r9_value, r9(orig_rax)),
         );
 
+        let now = monotonic();
         RegistersData {
             timestamp: Local::now(),
-            orig_rax: registers.orig_rax,
-            rax: registers.rax,
+            pid,
+            process: pid,
+            orig_rax,
+            rax,
             rdi,
             rsi,
             rdx,
             r10,
             r8,
             r9,
+            injected: false,
+            unfinished: false,
+            tampered: vec![],
+            violation: None,
+            entry: now,
+            exit: now,
+            relative: Duration::ZERO,
+            delta: Duration::ZERO,
+            stack: vec![],
+        }
+    }
+
+    /// Create `RegistersData` of a recorded syscall from the values of its registers, as in
+    /// `from_raw`, where `strings` are the strings pointed by the arguments as
+    /// (argument index, string)
+    pub fn load(
+        pid: Pid,
+        timestamp: DateTime<Local>,
+        (entry, exit): (Duration, Duration),
+        registers: [u64; 8],
+        strings: Vec<(usize, String)>,
+    ) -> RegistersData {
+        let mut reg = RegistersData::from_raw(pid, registers);
+        reg.timestamp = timestamp;
+        reg.entry = entry;
+        reg.exit = exit;
+
+        let mut regs = [
+            &mut reg.rdi,
+            &mut reg.rsi,
+            &mut reg.rdx,
+            &mut reg.r10,
+            &mut reg.r8,
+            &mut reg.r9,
+        ];
+        for (index, string) in strings {
+            if let Some(output) = regs.get_mut(index) {
+                output.captured = Some(string);
+            }
+        }
+
+        reg
+    }
+
+    /// Set the time since the start of the tracing and since the previous shown syscall
+    pub fn set_relative(&mut self, relative: Duration, delta: Duration) {
+        self.relative = relative;
+        self.delta = delta;
+    }
+
+    /// Get the timestamp of the line in the format defined by `args`, in UTC or in the local
+    /// timezone
+    pub fn date(&self, args: &Args) -> Option<String> {
+        let format = |format: &str| {
+            if args.utc {
+                self.timestamp
+                    .with_timezone(&Utc)
+                    .format(format)
+                    .to_string()
+            } else {
+                self.timestamp.format(format).to_string()
+            }
+        };
+
+        let timestamps = args.timestamps.unwrap_or(match args.format {
+            OutputFormat::Strace => TimestampFormat::None,
+            _ => TimestampFormat::Rfc3339,
+        });
+
+        match timestamps {
+            TimestampFormat::None => None,
+            TimestampFormat::Time => Some(format("%H:%M:%S")),
+            TimestampFormat::Us => Some(format("%H:%M:%S%.6f")),
+            TimestampFormat::Unix => Some(format("%s%.6f")),
+            TimestampFormat::Relative => Some(format!("{:.6}", self.relative.as_secs_f64())),
+            TimestampFormat::Delta => Some(format!("{:.6}", self.delta.as_secs_f64())),
+            TimestampFormat::Rfc3339 => Some(format("%+")),
+        }
+    }
+
+    /// Read now the strings pointed by the arguments, for a syscall which replaces the memory of
+    /// the process, like `execve`
+    pub fn capture(&mut self) {
+        for reg in [
+            &mut self.rdi,
+            &mut self.rsi,
+            &mut self.rdx,
+            &mut self.r10,
+            &mut self.r8,
+            &mut self.r9,
+        ] {
+            if is_string(reg.argument) && reg.raw != 0 {
+                reg.captured = Some(read_string(self.pid, reg.raw));
+            }
         }
     }
 
-    /// Get date in ISO 8601 / RFC 3339 date & time string format
-    pub fn date(&self) -> String {
-        self.timestamp.format("%+").to_string()
+    /// Set the arguments of the syscall to the ones of `entry`, taken at its entry, for a
+    /// syscall which replaces the registers, like `execve`
+    pub fn set_arguments(&mut self, entry: &RegistersData) {
+        self.rdi = entry.rdi.clone();
+        self.rsi = entry.rsi.clone();
+        self.rdx = entry.rdx.clone();
+        self.r10 = entry.r10.clone();
+        self.r8 = entry.r8.clone();
+        self.r9 = entry.r9.clone();
+    }
+
+    /// Set the process of the thread which made the syscall
+    pub fn set_process(&mut self, process: Pid) {
+        self.process = process;
+    }
+
+    /// Mark the syscall as injected: it has been skipped and its result set by the tracer
+    pub fn set_injected(&mut self) {
+        self.injected = true;
+    }
+
+    /// Mark the syscall as unfinished: it doesn't return, so it has no result
+    pub fn set_unfinished(&mut self) {
+        self.unfinished = true;
+    }
+
+    /// Returns if the syscall doesn't return, so it has no result
+    pub fn unfinished(&self) -> bool {
+        self.unfinished
+    }
+
+    /// Record that the tracer changed an argument or the return value of the syscall
+    pub fn set_tampered(&mut self, argument: String, original: u64, modified: u64) {
+        self.tampered.push((argument, original, modified));
+    }
+
+    /// Mark the syscall as a violation of the policy
+    pub fn set_violation(&mut self, reason: String) {
+        self.violation = Some(reason);
+    }
+
+    /// Set the stack of the process at the entry of the syscall
+    pub fn set_stack(&mut self, stack: Vec<Frame>) {
+        self.stack = stack;
+    }
+
+    /// Returns the stack of the process at the entry of the syscall, innermost frame first
+    pub fn stack(&self) -> &[Frame] {
+        &self.stack
+    }
+
+    /// Returns a description of the values changed by the tracer, eg: "count: 10 -> 1"
+    pub fn tampered_output(&self) -> String {
+        self.tampered
+            .iter()
+            .map(|(argument, original, modified)| {
+                if argument == "ret" {
+                    format!("{argument}: 0x{original:x} -> 0x{modified:x}")
+                } else {
+                    format!("{argument}: {original} -> {modified}")
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Returns if the result of the syscall has been injected by the tracer
+    pub fn injected(&self) -> bool {
+        self.injected
+    }
+
+    /// Returns why the syscall is a violation of the policy, if it is one
+    pub fn violation(&self) -> Option<&str> {
+        self.violation.as_deref()
+    }
+
+    /// Returns the wall clock time at the exit of the syscall
+    pub fn timestamp(&self) -> DateTime<Local> {
+        self.timestamp
+    }
+
+    /// Returns the thread which made the syscall
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// Returns the process of the thread which made the syscall
+    pub fn process(&self) -> Pid {
+        self.process
+    }
+
+    /// Returns the syscall number
+    pub fn number(&self) -> u64 {
+        self.orig_rax
+    }
+
+    /// Set the time of the entry of the syscall, since the registers are read at its exit
+    pub fn set_entry(&mut self, entry: Duration) {
+        self.entry = entry;
+    }
+
+    /// Returns the time of the entry of the syscall from a monotonic clock
+    pub fn entry(&self) -> Duration {
+        self.entry
+    }
+
+    /// Returns the time spent from the entry to the exit of the syscall
+    pub fn duration(&self) -> Duration {
+        self.exit.saturating_sub(self.entry)
     }
 
     /// Return the rax name as syscall name
@@ -80,14 +421,180 @@ impl RegistersData {
         syscall_name(self.orig_rax)
     }
 
-    /// Returns a good string which shows the output for a line
-    pub fn output(&mut self, pid: Pid) -> String {
-        let mut output = format!("[{}]: ", self.date());
+    /// Returns the list of arguments as (argument, raw value) used by the syscall
+    pub fn args(&self) -> Vec<(&'static str, u64)> {
+        [
+            &self.rdi, &self.rsi, &self.rdx, &self.r10, &self.r8, &self.r9,
+        ]
+        .iter()
+        .filter(|reg| !reg.argument.is_empty())
+        .map(|reg| (reg.argument, reg.raw))
+        .collect()
+    }
+
+    /// Returns the argument at `index`, in the order of the registers `rdi`, `rsi`, `rdx`, `r10`,
+    /// `r8` and `r9`
+    pub fn argument(&self, index: usize) -> &'static str {
+        [
+            &self.rdi, &self.rsi, &self.rdx, &self.r10, &self.r8, &self.r9,
+        ]
+        .get(index)
+        .map(|reg| reg.argument)
+        .unwrap_or_default()
+    }
+
+    /// Returns the string pointed by an argument at `address`, captured if the syscall has
+    /// been recorded or read from the memory of the process
+    pub fn string(&self, address: u64) -> String {
+        [
+            &self.rdi, &self.rsi, &self.rdx, &self.r10, &self.r8, &self.r9,
+        ]
+        .iter()
+        .find(|reg| reg.raw == address && reg.captured.is_some())
+        .map(|reg| reg.string(self.pid))
+        .unwrap_or_else(|| read_string(self.pid, address))
+    }
+
+    /// Returns `length` bytes pointed by an argument at `address`, captured if the syscall has
+    /// been recorded or read from the memory of the process
+    pub fn bytes(&self, address: u64, length: usize) -> Vec<u8> {
+        match [
+            &self.rdi, &self.rsi, &self.rdx, &self.r10, &self.r8, &self.r9,
+        ]
+        .iter()
+        .find_map(|reg| reg.captured.as_ref().filter(|_| reg.raw == address))
+        {
+            Some(captured) => captured.bytes().take(length).collect(),
+            None => read_bytes(self.pid, address, length),
+        }
+    }
+
+    /// Returns the value of `rax` at the exit of the syscall as a signed value
+    pub fn retval(&self) -> i64 {
+        self.rax as i64
+    }
+
+    /// Returns the value of `rax` at the exit of the syscall in hexadecimal, or `?` if it
+    /// doesn't return
+    fn result(&self) -> String {
+        if self.unfinished {
+            "?".to_string()
+        } else {
+            format!("0x{:x}", self.rax)
+        }
+    }
+
+    /// Returns the error of the syscall if it failed, reading the `rax` value at its exit as
+    /// a negative errno in the range `-4095..=-1`
+    pub fn errno(&self) -> Option<Errno> {
+        let rax = self.retval();
+        if !self.unfinished && (-4095..0).contains(&rax) {
+            Some(Errno::from_i32(-rax as i32))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the structured representation of the syscall, reading the full strings from
+    /// the memory of the process, with its timestamp in UTC if `utc` or in the local timezone
+    pub fn event(&self, utc: bool) -> Event {
+        let args = [
+            &self.rdi, &self.rsi, &self.rdx, &self.r10, &self.r8, &self.r9,
+        ]
+        .iter()
+        .filter(|reg| !reg.argument.is_empty())
+        .map(|reg| {
+            let name = argument_name(reg.argument);
+            let value = if is_string(reg.argument) {
+                Value::String(reg.string(self.pid))
+            } else if reg.argument.starts_with("int ") {
+                Value::Signed(reg.raw as i32 as i64)
+            } else if reg.argument.starts_with("long ") {
+                Value::Signed(reg.raw as i64)
+            } else {
+                Value::Unsigned(reg.raw)
+            };
+
+            let mut kind = reg.argument.to_string();
+            if let Some(index) = kind.rfind(name) {
+                kind.replace_range(index..index + name.len(), "");
+            }
+
+            Argument {
+                name: name.to_string(),
+                kind: kind.trim().to_string(),
+                value,
+            }
+        })
+        .collect();
+
+        Event {
+            pid: self.process.as_raw(),
+            tid: self.pid.as_raw(),
+            timestamp: if utc {
+                self.timestamp.with_timezone(&Utc).to_rfc3339()
+            } else {
+                self.timestamp.to_rfc3339()
+            },
+            entry_ns: self.entry.as_nanos() as u64,
+            exit_ns: self.exit.as_nanos() as u64,
+            duration_ns: self.duration().as_nanos() as u64,
+            number: self.orig_rax,
+            name: self.name().to_string(),
+            args,
+            registers: Registers {
+                orig_rax: self.orig_rax,
+                rdi: self.rdi.raw,
+                rsi: self.rsi.raw,
+                rdx: self.rdx.raw,
+                r10: self.r10.raw,
+                r8: self.r8.raw,
+                r9: self.r9.raw,
+                rax: self.rax,
+            },
+            retval: if self.unfinished { 0 } else { self.retval() },
+            errno: self.errno().map(|errno| format!("{errno:?}")),
+            injected: self.injected,
+            unfinished: self.unfinished,
+            tampered: self
+                .tampered
+                .iter()
+                .map(|(argument, original, modified)| Tampered {
+                    argument: argument.clone(),
+                    original: *original,
+                    modified: *modified,
+                })
+                .collect(),
+            violation: self.violation.clone(),
+        }
+    }
+
+    /// Returns a good string which shows the output for a line, with ANSI colors if `color`,
+    /// starting with `[pid N]` if the syscall has not been made by the traced process `pid`
+    pub fn output(&mut self, pid: Pid, args: &Args, color: bool) -> String {
+        let paint = |style: Paint| if color { style } else { Paint::new() };
+
+        let mut output = match self.date(args) {
+            Some(date) => format!("[{date}]: "),
+            None => String::new(),
+        };
+        if self.pid != pid {
+            output.push_str(&format!(
+                "{} ",
+                format!("[pid {:>5}]", self.pid).style(paint(Paint::new().cyan()))
+            ));
+        }
 
         if !self.name().is_empty() {
-            output.push_str(&format!("{}(", self.name().bold()));
+            output.push_str(&format!(
+                "{}(",
+                self.name().style(paint(Paint::new().bold()))
+            ));
         } else {
-            output.push_str(&format!("{}(", self.orig_rax.yellow().bold()));
+            output.push_str(&format!(
+                "{}(",
+                self.orig_rax.style(paint(Paint::new().yellow().bold()))
+            ));
         }
 
         let mut has_reg = false;
@@ -104,15 +611,16 @@ impl RegistersData {
         for reg in &mut regs {
             if !reg.argument.is_empty() {
                 let output_reg = reg.argument.to_owned() + ":";
-                reg.value = if (output_reg.starts_with("const char *")
-                    || output_reg.starts_with("char *"))
-                    && !reg.value.starts_with("\"")
-                {
-                    read_memory(pid, reg.value.parse::<u64>().unwrap())
+                reg.value = if is_string(reg.argument) && !reg.value.starts_with("\"") {
+                    quote_string(reg.string(self.pid))
                 } else {
                     reg.value.to_string()
                 };
-                output.push_str(&format!("{} {}, ", output_reg.blue(), reg.value));
+                output.push_str(&format!(
+                    "{} {}, ",
+                    output_reg.style(paint(Paint::new().blue())),
+                    reg.value
+                ));
                 has_reg = true;
             }
         }
@@ -122,14 +630,38 @@ impl RegistersData {
             output.remove(output.len() - 1);
         }
 
-        output.push_str(&format!(") = 0x{:x}", self.rax)[..]);
+        output.push_str(&format!(") = {}", self.result()));
+        if !self.unfinished {
+            output.push_str(&format!(" <{:.6}>", self.duration().as_secs_f64()));
+        }
+        if self.injected {
+            output.push_str(&format!(
+                " {}",
+                "(INJECTED)".style(paint(Paint::new().magenta().bold()))
+            ));
+        }
+        if !self.tampered.is_empty() {
+            output.push_str(&format!(
+                " {}",
+                format!("(TAMPERED {})", self.tampered_output())
+                    .style(paint(Paint::new().magenta().bold()))
+            ));
+        }
+        if let Some(violation) = &self.violation {
+            output.push_str(&format!(
+                " {}",
+                format!("(VIOLATION: {violation})").style(paint(Paint::new().red().bold()))
+            ));
+        }
         output
     }
 
     /// Returns a good line for TUI
-    pub fn output_ui(&mut self, _pid: Pid) -> Line {
+    pub fn output_ui(&mut self, args: &Args) -> Line<'_> {
         let mut spans: Vec<Span> = vec![];
-        spans.push(Span::raw(format!("[{}]: ", self.date())));
+        if let Some(date) = self.date(args) {
+            spans.push(Span::raw(format!("[{date}]: ")));
+        }
         if !self.name().is_empty() {
             spans.push(Span::styled(
                 format!("{}(", self.name()),
@@ -175,9 +707,37 @@ impl RegistersData {
         }
 
         spans.push(Span::styled(
-            format!(") = 0x{:x}", self.rax),
+            format!(") = {}", self.result()),
             Style::default(),
         ));
+        if !self.unfinished {
+            spans.push(Span::styled(
+                format!(" <{:.6}>", self.duration().as_secs_f64()),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if self.injected {
+            spans.push(Span::styled(
+                " (INJECTED)",
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        if !self.tampered.is_empty() {
+            spans.push(Span::styled(
+                format!(" (TAMPERED {})", self.tampered_output()),
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        if let Some(violation) = &self.violation {
+            spans.push(Span::styled(
+                format!(" (VIOLATION: {violation})"),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
         Line::from(spans)
     }
 }
@@ -0,0 +1,192 @@
+use crate::{
+    cli::Args,
+    event::{Event, Value},
+    fds::FdTable,
+    filter::is_selected,
+    record::{Reader, Record, MAGIC},
+    registers::RegistersData,
+    trace::{run, ProcessEvent, Syscalls},
+};
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use nix::unistd::Pid;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+    time::Duration,
+};
+
+/// File from which a trace is loaded
+enum Source {
+    /// Binary recording made with `--record`
+    Recording(Reader<BufReader<File>>),
+    /// Output of `--format jsonl`
+    Jsonl(Lines<BufReader<File>>),
+}
+
+/// Create `RegistersData` from a syscall of a JSON Lines trace
+fn from_event(event: Event) -> anyhow::Result<RegistersData> {
+    let timestamp = DateTime::parse_from_rfc3339(&event.timestamp)?.with_timezone(&Local);
+    let strings = event
+        .args
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, argument)| match argument.value {
+            Value::String(string) => Some((index, string)),
+            _ => None,
+        })
+        .collect();
+
+    let mut reg = RegistersData::load(
+        Pid::from_raw(event.tid),
+        timestamp,
+        (
+            Duration::from_nanos(event.entry_ns),
+            Duration::from_nanos(event.exit_ns),
+        ),
+        [
+            event.registers.orig_rax,
+            event.registers.rdi,
+            event.registers.rsi,
+            event.registers.rdx,
+            event.registers.r10,
+            event.registers.r8,
+            event.registers.r9,
+            event.registers.rax,
+        ],
+        strings,
+    );
+    if event.injected {
+        reg.set_injected();
+    }
+    if event.unfinished {
+        reg.set_unfinished();
+    }
+    for tampered in event.tampered {
+        reg.set_tampered(tampered.argument, tampered.original, tampered.modified);
+    }
+    if let Some(violation) = event.violation {
+        reg.set_violation(violation);
+    }
+
+    Ok(reg)
+}
+
+/// Syscalls of a trace loaded from a file, selected by the same filters of a live tracing
+pub struct Replay<'a> {
+    args: &'a Args,
+    source: Source,
+    /// Record read in advance to know the traced process
+    peeked: Option<Record>,
+    /// Number of the last line read of a JSON Lines trace
+    line: usize,
+    fds: FdTable,
+    events: Vec<ProcessEvent>,
+    /// Time of the entry of the first syscall, from a monotonic clock
+    start: Option<Duration>,
+    /// Time of the entry of the previous shown syscall, from a monotonic clock
+    previous: Duration,
+}
+
+impl<'a> Replay<'a> {
+    /// Open a trace recorded with `--record` or written with `--format jsonl`
+    pub fn open(path: &str, args: &'a Args) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(File::open(path).with_context(|| path.to_string())?);
+        let source = if reader.fill_buf()?.starts_with(MAGIC) {
+            Source::Recording(Reader::new(reader).with_context(|| path.to_string())?)
+        } else {
+            Source::Jsonl(reader.lines())
+        };
+
+        let mut replay = Self {
+            args,
+            source,
+            peeked: None,
+            line: 0,
+            fds: FdTable::offline(),
+            events: vec![],
+            start: None,
+            previous: Duration::ZERO,
+        };
+        replay.peeked = replay.read().with_context(|| path.to_string())?;
+
+        Ok(replay)
+    }
+
+    /// Returns the process which made the first syscall of the trace
+    pub fn pid(&self) -> Pid {
+        self.peeked
+            .as_ref()
+            .map(|record| record.pid())
+            .unwrap_or(Pid::from_raw(0))
+    }
+
+    /// Returns the next record of the trace
+    fn read(&mut self) -> anyhow::Result<Option<Record>> {
+        if let Some(record) = self.peeked.take() {
+            return Ok(Some(record));
+        }
+
+        match &mut self.source {
+            Source::Recording(reader) => reader.next(),
+            Source::Jsonl(lines) => {
+                for line in lines.by_ref() {
+                    self.line += 1;
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        let event: Event = serde_json::from_str(&line)
+                            .with_context(|| format!("line {}", self.line))?;
+                        return Ok(Some(Record::Syscall(Box::new(from_event(event)?))));
+                    }
+                }
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Syscalls for Replay<'_> {
+    fn next(&mut self) -> anyhow::Result<Option<RegistersData>> {
+        while let Some(record) = self.read()? {
+            let mut reg = match record {
+                Record::Syscall(reg) => *reg,
+                Record::Process(event) => {
+                    self.events.push(event);
+                    continue;
+                }
+            };
+
+            let entry = reg.entry();
+            let start = *self.start.get_or_insert(entry);
+            if self.previous.is_zero() {
+                self.previous = start;
+            }
+
+            let selected = is_selected(self.args, &reg, &self.fds);
+            self.fds.update(&reg);
+            if selected {
+                reg.set_relative(
+                    entry.saturating_sub(start),
+                    entry.saturating_sub(self.previous),
+                );
+                self.previous = entry;
+                return Ok(Some(reg));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn process_events(&mut self) -> anyhow::Result<Vec<ProcessEvent>> {
+        Ok(std::mem::take(&mut self.events))
+    }
+}
+
+/// Load the trace at `path`, writing its syscalls as a live tracing does
+pub fn replay(path: &str, args: &Args) -> anyhow::Result<Vec<RegistersData>> {
+    let mut replay = Replay::open(path, args)?;
+    let pid = replay.pid();
+
+    run(&mut replay, pid, args)
+}
@@ -0,0 +1,340 @@
+use crate::symbols::{MappingKey, Symbol, Symbolizer};
+use gimli::{
+    BaseAddresses, CfaRule, EhFrame, EhFrameHdr, LittleEndian, RegisterRule, UnwindContext,
+    UnwindSection, X86_64,
+};
+use nix::{libc::user_regs_struct, sys::ptrace, unistd::Pid};
+use object::{Object, ObjectSection, ObjectSegment};
+use std::{collections::HashMap, ffi::c_void, fmt, fs};
+
+/// Maximum number of frames unwound from a stack
+const MAX_FRAMES: usize = 64;
+
+/// Frame of the stack of a process at the entry of a syscall
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Address of the instruction, the return address for the callers
+    pub address: u64,
+    /// File mapped at the address
+    pub path: Option<String>,
+    /// Offset of the address in the file
+    pub offset: u64,
+    /// Function which contains the address
+    pub symbol: Option<Symbol>,
+}
+
+impl fmt::Display for Frame {
+    /// Write the frame like `strace -k`, eg: `/usr/lib/libc.so.6(write+0x14) [0x11c574]`,
+    /// followed by the source location when there is debug info, eg: `at src/main.rs:12`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(path) = &self.path else {
+            return write!(f, "unexpected_backtracing_error [{:#x}]", self.address);
+        };
+
+        match &self.symbol {
+            Some(symbol) => write!(f, "{path}({symbol}) [{:#x}]", self.offset)?,
+            None => write!(f, "{path}() [{:#x}]", self.offset)?,
+        }
+
+        match self
+            .symbol
+            .as_ref()
+            .and_then(|symbol| symbol.location.as_ref())
+        {
+            Some((file, line)) => write!(f, " at {file}:{line}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Region of the memory of a process where a file is mapped, from `/proc/<pid>/maps`
+#[derive(Debug)]
+struct Mapping {
+    start: u64,
+    end: u64,
+    /// Offset in the file of the start of the region
+    offset: u64,
+    file: MappingKey,
+}
+
+/// Returns the executable regions of the memory of `pid` which map a file
+fn mappings(pid: Pid) -> Vec<Mapping> {
+    let Ok(maps) = fs::read_to_string(format!("/proc/{pid}/maps")) else {
+        return vec![];
+    };
+
+    maps.lines()
+        .filter_map(|line| {
+            // Eg: `7f2c1a228000-7f2c1a3bd000 r-xp 00028000 fd:01 1055 /usr/lib/libc.so.6`
+            let mut fields = line.splitn(6, ' ');
+            let (start, end) = fields.next()?.split_once('-')?;
+            let permissions = fields.next()?;
+            let offset = fields.next()?;
+            let inode = fields.nth(1)?;
+            let path = fields.next()?.trim_start();
+            if !permissions.contains('x') || !path.starts_with('/') {
+                return None;
+            }
+
+            Some(Mapping {
+                start: u64::from_str_radix(start, 16).ok()?,
+                end: u64::from_str_radix(end, 16).ok()?,
+                offset: u64::from_str_radix(offset, 16).ok()?,
+                file: MappingKey {
+                    path: path.to_string(),
+                    inode: inode.parse().ok()?,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Section of an ELF file loaded at an address
+#[derive(Debug, Default)]
+struct Section {
+    address: u64,
+    data: Vec<u8>,
+}
+
+/// What is needed from an ELF file to unwind its frames, with the addresses as defined in the
+/// file
+#[derive(Debug, Default)]
+struct Module {
+    /// Loaded segments as (offset in the file, size in the file, address)
+    segments: Vec<(u64, u64, u64)>,
+    eh_frame: Section,
+    eh_frame_hdr: Section,
+    text: u64,
+}
+
+impl Module {
+    /// Load the ELF file at `path`, or an empty module if it can't be parsed
+    fn load(path: &str) -> Self {
+        let Ok(data) = fs::read(path) else {
+            return Self::default();
+        };
+        let Ok(file) = object::File::parse(&*data) else {
+            return Self::default();
+        };
+
+        let section = |name| {
+            file.section_by_name(name)
+                .and_then(|section| {
+                    Some(Section {
+                        address: section.address(),
+                        data: section.data().ok()?.to_vec(),
+                    })
+                })
+                .unwrap_or_default()
+        };
+
+        Self {
+            segments: file
+                .segments()
+                .map(|segment| {
+                    let (offset, size) = segment.file_range();
+                    (offset, size, segment.address())
+                })
+                .collect(),
+            eh_frame: section(".eh_frame"),
+            eh_frame_hdr: section(".eh_frame_hdr"),
+            text: file
+                .section_by_name(".text")
+                .map(|text| text.address())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the address in the file of the `offset` of the file
+    fn address(&self, offset: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .find(|(start, size, _)| (*start..start + size).contains(&offset))
+            .map(|(start, _, address)| address + offset - start)
+    }
+}
+
+/// Registers needed to unwind a frame
+#[derive(Clone, Copy, Debug)]
+struct Registers {
+    rip: u64,
+    rsp: u64,
+    rbp: u64,
+}
+
+/// Unwinder of the stacks of the traced processes, keeping the ELF files it loaded by mapped
+/// file, so a file replaced at the same path is loaded again
+pub struct Unwinder {
+    mappings: HashMap<Pid, Vec<Mapping>>,
+    modules: HashMap<MappingKey, Module>,
+    context: Box<UnwindContext<usize>>,
+    symbolizer: Symbolizer,
+}
+
+impl Unwinder {
+    pub fn new() -> Self {
+        Self {
+            mappings: HashMap::new(),
+            modules: HashMap::new(),
+            context: Box::new(UnwindContext::new()),
+            symbolizer: Symbolizer::new(),
+        }
+    }
+
+    /// Returns the index of the mapping of `pid` which contains `address`, reading the mappings
+    /// again if it is not found as a library could have been loaded since
+    fn mapping(&mut self, pid: Pid, address: u64) -> Option<usize> {
+        let find = |mappings: &[Mapping]| {
+            mappings
+                .iter()
+                .position(|mapping| (mapping.start..mapping.end).contains(&address))
+        };
+
+        if let Some(index) = self.mappings.get(&pid).and_then(|mappings| find(mappings)) {
+            return Some(index);
+        }
+
+        let mappings = self.mappings.entry(pid).or_default();
+        *mappings = self::mappings(pid);
+        find(mappings)
+    }
+
+    /// Unwind the stack of `pid` from its `registers`, with the `.eh_frame` of the ELF files or
+    /// with the frame pointers when there isn't one
+    pub fn unwind(&mut self, pid: Pid, registers: &user_regs_struct) -> Vec<Frame> {
+        let mut frames = vec![];
+        let mut registers = Registers {
+            rip: registers.rip,
+            rsp: registers.rsp,
+            rbp: registers.rbp,
+        };
+
+        while registers.rip != 0 && frames.len() < MAX_FRAMES {
+            // The return address of a caller is after its call, which can be the last
+            // instruction of the function
+            let lookup = if frames.is_empty() {
+                registers.rip
+            } else {
+                registers.rip - 1
+            };
+
+            let mut frame = Frame {
+                address: registers.rip,
+                path: None,
+                offset: 0,
+                symbol: None,
+            };
+            let mut found = None;
+            if let Some(index) = self.mapping(pid, lookup) {
+                let mapping = &self.mappings[&pid][index];
+                let offset = lookup - mapping.start + mapping.offset;
+                let module = self
+                    .modules
+                    .entry(mapping.file.clone())
+                    .or_insert_with(|| Module::load(&mapping.file.path));
+
+                frame.path = Some(mapping.file.path.clone());
+                frame.offset = registers.rip - mapping.start + mapping.offset;
+                if let Some(address) = module.address(offset) {
+                    frame.symbol = self.symbolizer.symbolize(&mapping.file, address);
+                    found = Some((mapping.file.clone(), address));
+                }
+            }
+            frames.push(frame);
+
+            let caller = match found {
+                Some((file, address)) => self
+                    .unwind_cfi(pid, &file, address, registers)
+                    .or_else(|| unwind_frame_pointer(pid, registers)),
+                None => unwind_frame_pointer(pid, registers),
+            };
+
+            match caller {
+                // The stack grows down, so a caller can't be below its callee
+                Some(caller) if caller.rsp > registers.rsp => registers = caller,
+                _ => break,
+            }
+        }
+
+        frames
+    }
+
+    /// Unwind a frame of `pid` at `address` of the mapped ELF `file` with its `.eh_frame`
+    fn unwind_cfi(
+        &mut self,
+        pid: Pid,
+        file: &MappingKey,
+        address: u64,
+        registers: Registers,
+    ) -> Option<Registers> {
+        let module = self.modules.get(file)?;
+        let eh_frame = EhFrame::new(&module.eh_frame.data, LittleEndian);
+        let bases = BaseAddresses::default()
+            .set_eh_frame(module.eh_frame.address)
+            .set_eh_frame_hdr(module.eh_frame_hdr.address)
+            .set_text(module.text);
+
+        // Search the binary table of `.eh_frame_hdr` if there is one, instead of every entry
+        let row = match EhFrameHdr::new(&module.eh_frame_hdr.data, LittleEndian).parse(&bases, 8) {
+            Ok(header) => header.table()?.unwind_info_for_address(
+                &eh_frame,
+                &bases,
+                &mut self.context,
+                address,
+                EhFrame::cie_from_offset,
+            ),
+            Err(_) => eh_frame.unwind_info_for_address(
+                &bases,
+                &mut self.context,
+                address,
+                EhFrame::cie_from_offset,
+            ),
+        }
+        .ok()?;
+
+        let cfa = match row.cfa() {
+            CfaRule::RegisterAndOffset { register, offset } => {
+                let value = match *register {
+                    X86_64::RSP => registers.rsp,
+                    X86_64::RBP => registers.rbp,
+                    _ => return None,
+                };
+                value.wrapping_add_signed(*offset)
+            }
+            CfaRule::Expression(_) => return None,
+        };
+
+        let rip = match row.register(X86_64::RA) {
+            RegisterRule::Offset(offset) => read(pid, cfa.wrapping_add_signed(offset))?,
+            _ => return None,
+        };
+        let rbp = match row.register(X86_64::RBP) {
+            RegisterRule::Offset(offset) => read(pid, cfa.wrapping_add_signed(offset))?,
+            _ => registers.rbp,
+        };
+
+        Some(Registers { rip, rsp: cfa, rbp })
+    }
+}
+
+/// Unwind a frame of `pid` following its frame pointer, saved at the start of the frame with
+/// the return address after it
+fn unwind_frame_pointer(pid: Pid, registers: Registers) -> Option<Registers> {
+    // `rbp` can be used as a general register, so it can hold any value
+    if registers.rbp == 0 {
+        return None;
+    }
+
+    Some(Registers {
+        rip: read(pid, registers.rbp.checked_add(8)?)?,
+        rsp: registers.rbp.checked_add(16)?,
+        rbp: read(pid, registers.rbp)?,
+    })
+}
+
+/// Read a word from the memory of `pid` at `address`
+fn read(pid: Pid, address: u64) -> Option<u64> {
+    ptrace::read(pid, address as *mut c_void)
+        .ok()
+        .map(|word| word as u64)
+}
@@ -0,0 +1,295 @@
+use crate::{fds::FdTable, registers::RegistersData};
+use clap::ValueEnum;
+use std::{collections::HashMap, fmt, time::Duration};
+
+/// Key used to sort the rows of the summary
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum SortBy {
+    /// Total time spent in the syscall, descending
+    Time,
+    /// Number of calls, descending
+    Count,
+    /// Syscall name, ascending
+    Name,
+}
+
+/// Statistics for a single syscall
+#[derive(Default)]
+struct Row {
+    calls: u64,
+    errors: u64,
+    total: Duration,
+}
+
+/// Summary of the syscalls made by a process, like `strace -c`
+pub struct Summary {
+    rows: HashMap<String, Row>,
+    sort: SortBy,
+}
+
+impl Summary {
+    pub fn new(sort: SortBy) -> Self {
+        Self {
+            rows: HashMap::new(),
+            sort,
+        }
+    }
+
+    /// Add a syscall, taken at its exit, to the statistics
+    pub fn add(&mut self, reg: &RegistersData) {
+        let name = if reg.name().is_empty() {
+            reg.number().to_string()
+        } else {
+            reg.name().to_string()
+        };
+
+        let row = self.rows.entry(name).or_default();
+        row.calls += 1;
+        row.total += reg.duration();
+        if reg.errno().is_some() {
+            row.errors += 1;
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    /// Print the summary as a table sorted by the `sort` key
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows: Vec<(&String, &Row)> = self.rows.iter().collect();
+        match self.sort {
+            SortBy::Time => rows.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0))),
+            SortBy::Count => rows.sort_by(|a, b| b.1.calls.cmp(&a.1.calls).then(a.0.cmp(b.0))),
+            SortBy::Name => rows.sort_by(|a, b| a.0.cmp(b.0)),
+        };
+
+        let total: Duration = rows.iter().map(|(_, row)| row.total).sum();
+        let calls: u64 = rows.iter().map(|(_, row)| row.calls).sum();
+        let errors: u64 = rows.iter().map(|(_, row)| row.errors).sum();
+        let percentage = |time: Duration| {
+            if total.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() * 100.0 / total.as_secs_f64()
+            }
+        };
+        let separator = "------ ----------- ----------- --------- --------- ----------------";
+
+        writeln!(
+            f,
+            "% time     seconds  usecs/call     calls    errors syscall"
+        )?;
+        writeln!(f, "{separator}")?;
+        for (name, row) in rows {
+            let errors = if row.errors > 0 {
+                row.errors.to_string()
+            } else {
+                String::new()
+            };
+            writeln!(
+                f,
+                "{:>6.2} {:>11.6} {:>11} {:>9} {:>9} {name}",
+                percentage(row.total),
+                row.total.as_secs_f64(),
+                row.total.as_micros() / row.calls as u128,
+                row.calls,
+                errors,
+            )?;
+        }
+        writeln!(f, "{separator}")?;
+        write!(
+            f,
+            "{:>6.2} {:>11.6} {:>11} {:>9} {:>9} total",
+            100.0,
+            total.as_secs_f64(),
+            if calls > 0 {
+                (total.as_micros() / calls as u128).to_string()
+            } else {
+                String::new()
+            },
+            calls,
+            errors,
+        )
+    }
+}
+
+/// Number of linear sub-buckets for every power of two of a `Histogram`
+const SUB_BUCKETS: u64 = 16;
+
+/// Format a duration with a short unit, eg: `12.3us`
+pub fn human(duration: Duration) -> String {
+    let ns = duration.as_nanos();
+    if ns < 1_000 {
+        format!("{ns}ns")
+    } else if ns < 1_000_000 {
+        format!("{:.1}us", ns as f64 / 1e3)
+    } else if ns < 1_000_000_000 {
+        format!("{:.1}ms", ns as f64 / 1e6)
+    } else {
+        format!("{:.2}s", ns as f64 / 1e9)
+    }
+}
+
+/// Histogram of latencies like an HDR histogram: buckets grow as powers of two and every one is
+/// split in `SUB_BUCKETS` linear sub-buckets, so the error is bounded to the 1/16 of a value
+#[derive(Clone, Default)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    max: Duration,
+}
+
+impl Histogram {
+    /// Returns the index of the sub-bucket for a value in nanoseconds
+    fn index(ns: u64) -> usize {
+        if ns < SUB_BUCKETS {
+            return ns as usize;
+        }
+
+        let exp = 63 - ns.leading_zeros() as u64;
+        let sub = (ns >> (exp - 4)) - SUB_BUCKETS;
+
+        ((exp - 3) * SUB_BUCKETS + sub) as usize
+    }
+
+    /// Returns the range of values in nanoseconds of a sub-bucket
+    fn range(index: usize) -> (u64, u64) {
+        let index = index as u64;
+        if index < SUB_BUCKETS {
+            return (index, index);
+        }
+
+        let exp = index / SUB_BUCKETS + 3;
+        let lower = (SUB_BUCKETS + index % SUB_BUCKETS) << (exp - 4);
+
+        (lower, lower + (1 << (exp - 4)) - 1)
+    }
+
+    pub fn add(&mut self, duration: Duration) {
+        let index = Self::index(duration.as_nanos() as u64);
+        if self.counts.len() <= index {
+            self.counts.resize(index + 1, 0);
+        }
+
+        self.counts[index] += 1;
+        self.count += 1;
+        self.max = self.max.max(duration);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns the value below which `percentile`% of the values fall
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let target = ((percentile / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+
+        let mut cumulative = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= target {
+                let (_, upper) = Self::range(index);
+                return Duration::from_nanos(upper).min(self.max);
+            }
+        }
+
+        self.max
+    }
+
+    /// Returns the counts grouped by powers of two, as (lower bound, count), from the first to
+    /// the last not empty group
+    pub fn powers(&self) -> Vec<(Duration, u64)> {
+        let mut powers: Vec<(Duration, u64)> = vec![];
+        for (index, count) in self.counts.iter().enumerate() {
+            let (lower, _) = Self::range(index);
+            let power = if lower == 0 {
+                0
+            } else {
+                1 << (63 - lower.leading_zeros())
+            };
+
+            match powers.last_mut() {
+                Some((last, total)) if last.as_nanos() as u64 == power => *total += count,
+                _ => powers.push((Duration::from_nanos(power), *count)),
+            }
+        }
+
+        let first = powers.iter().position(|(_, count)| *count > 0);
+        let last = powers.iter().rposition(|(_, count)| *count > 0);
+        match (first, last) {
+            (Some(first), Some(last)) => powers[first..=last].to_vec(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Latency histograms for every syscall, optionally split by file descriptor
+pub struct Latency {
+    histograms: HashMap<String, Histogram>,
+    by_fd: bool,
+}
+
+impl Latency {
+    pub fn new(by_fd: bool) -> Self {
+        Self {
+            histograms: HashMap::new(),
+            by_fd,
+        }
+    }
+
+    /// Add a syscall, taken at its exit, to the histograms
+    pub fn add(&mut self, reg: &RegistersData) {
+        let mut name = if reg.name().is_empty() {
+            reg.number().to_string()
+        } else {
+            reg.name().to_string()
+        };
+
+        if self.by_fd {
+            if let Some(fd) = FdTable::fds(reg).first() {
+                name.push_str(&format!("(fd={fd})"));
+            }
+        }
+
+        self.histograms.entry(name).or_default().add(reg.duration());
+    }
+
+    /// Returns the histogram of a syscall
+    pub fn histogram(&self, name: &str) -> Option<&Histogram> {
+        self.histograms.get(name)
+    }
+}
+
+impl fmt::Display for Latency {
+    /// Print the percentiles of every syscall sorted by the 99th percentile
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut histograms: Vec<(&String, &Histogram)> = self.histograms.iter().collect();
+        histograms.sort_by(|a, b| {
+            b.1.percentile(99.0)
+                .cmp(&a.1.percentile(99.0))
+                .then(a.0.cmp(b.0))
+        });
+
+        write!(
+            f,
+            "{:>9} {:>9} {:>9} {:>9} {:>9} syscall",
+            "calls", "p50", "p90", "p99", "max"
+        )?;
+        for (name, histogram) in histograms {
+            write!(
+                f,
+                "\n{:>9} {:>9} {:>9} {:>9} {:>9} {name}",
+                histogram.count(),
+                human(histogram.percentile(50.0)),
+                human(histogram.percentile(90.0)),
+                human(histogram.percentile(99.0)),
+                human(histogram.max()),
+            )?;
+        }
+
+        Ok(())
+    }
+}
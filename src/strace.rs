@@ -0,0 +1,251 @@
+use crate::{
+    cli::Args,
+    fds::AT_FDCWD,
+    registers::{argument_name, is_string, RegistersData},
+};
+use nix::{libc, unistd::Pid};
+
+/// Maximum number of bytes shown for a string, like the default of `strace -s`
+const STRING_LIMIT: usize = 32;
+
+/// Flags of `open`, `openat` and `creat` besides the access mode
+const OPEN_FLAGS: [(i32, &str); 16] = [
+    (libc::O_CREAT, "O_CREAT"),
+    (libc::O_EXCL, "O_EXCL"),
+    (libc::O_NOCTTY, "O_NOCTTY"),
+    (libc::O_TRUNC, "O_TRUNC"),
+    (libc::O_APPEND, "O_APPEND"),
+    (libc::O_NONBLOCK, "O_NONBLOCK"),
+    (libc::O_SYNC, "O_SYNC"),
+    (libc::O_DSYNC, "O_DSYNC"),
+    (libc::O_ASYNC, "O_ASYNC"),
+    (libc::O_DIRECT, "O_DIRECT"),
+    (libc::O_LARGEFILE, "O_LARGEFILE"),
+    (libc::O_TMPFILE, "O_TMPFILE"),
+    (libc::O_DIRECTORY, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW, "O_NOFOLLOW"),
+    (libc::O_NOATIME, "O_NOATIME"),
+    (libc::O_CLOEXEC, "O_CLOEXEC"),
+];
+
+const PROT_FLAGS: [(i32, &str); 3] = [
+    (libc::PROT_READ, "PROT_READ"),
+    (libc::PROT_WRITE, "PROT_WRITE"),
+    (libc::PROT_EXEC, "PROT_EXEC"),
+];
+
+const MAP_FLAGS: [(i32, &str); 10] = [
+    (libc::MAP_SHARED, "MAP_SHARED"),
+    (libc::MAP_PRIVATE, "MAP_PRIVATE"),
+    (libc::MAP_FIXED, "MAP_FIXED"),
+    (libc::MAP_ANONYMOUS, "MAP_ANONYMOUS"),
+    (libc::MAP_DENYWRITE, "MAP_DENYWRITE"),
+    (libc::MAP_NORESERVE, "MAP_NORESERVE"),
+    (libc::MAP_POPULATE, "MAP_POPULATE"),
+    (libc::MAP_STACK, "MAP_STACK"),
+    (libc::MAP_HUGETLB, "MAP_HUGETLB"),
+    (libc::MAP_FIXED_NOREPLACE, "MAP_FIXED_NOREPLACE"),
+];
+
+const ACCESS_FLAGS: [(i32, &str); 3] = [
+    (libc::R_OK, "R_OK"),
+    (libc::W_OK, "W_OK"),
+    (libc::X_OK, "X_OK"),
+];
+
+const AT_FLAGS: [(i32, &str); 5] = [
+    (libc::AT_SYMLINK_NOFOLLOW, "AT_SYMLINK_NOFOLLOW"),
+    (libc::AT_REMOVEDIR, "AT_REMOVEDIR"),
+    (libc::AT_SYMLINK_FOLLOW, "AT_SYMLINK_FOLLOW"),
+    (libc::AT_NO_AUTOMOUNT, "AT_NO_AUTOMOUNT"),
+    (libc::AT_EMPTY_PATH, "AT_EMPTY_PATH"),
+];
+
+const SEEK_WHENCE: [&str; 5] = ["SEEK_SET", "SEEK_CUR", "SEEK_END", "SEEK_DATA", "SEEK_HOLE"];
+
+/// Syscalls which return an address, shown in hexadecimal
+const ADDRESS_RETURNS: [&str; 4] = ["mmap", "brk", "mremap", "shmat"];
+
+/// Returns the names of the bits set in `value` joined by `|`, like `PROT_READ|PROT_WRITE`, with
+/// the unknown bits in hexadecimal
+fn flags(value: u64, names: &[(i32, &str)]) -> Vec<String> {
+    let mut value = value as u32;
+    let mut output = vec![];
+    for (flag, name) in names {
+        let flag = *flag as u32;
+        if flag != 0 && value & flag == flag {
+            output.push(name.to_string());
+            value &= !flag;
+        }
+    }
+
+    if value != 0 {
+        output.push(format!("{value:#x}"));
+    }
+
+    output
+}
+
+/// Returns a mode in octal with a leading 0 like strace, eg: `0644`, or `000` if it is 0
+fn octal(mode: u64) -> String {
+    format!("0{mode:02o}")
+}
+
+/// Quote a string escaping it like strace, truncated to `STRING_LIMIT` bytes
+fn quote(bytes: &[u8]) -> String {
+    let mut output = String::from("\"");
+    for byte in bytes.iter().take(STRING_LIMIT) {
+        match byte {
+            b'"' => output.push_str("\\\""),
+            b'\\' => output.push_str("\\\\"),
+            b'\n' => output.push_str("\\n"),
+            b'\t' => output.push_str("\\t"),
+            b'\r' => output.push_str("\\r"),
+            b' '..=b'~' => output.push(*byte as char),
+            _ => output.push_str(&format!("\\{byte:o}")),
+        }
+    }
+    output.push('"');
+
+    if bytes.len() > STRING_LIMIT {
+        output.push_str("...");
+    }
+
+    output
+}
+
+/// Decode an argument of a syscall, taken at its exit, like strace does. Returns `None` if the
+/// argument is not shown
+fn argument(reg: &RegistersData, argument: &str, raw: u64) -> Option<String> {
+    let syscall = reg.name();
+    let name = argument_name(argument);
+    let flags_of = |names: &[(i32, &str)]| match flags(raw, names) {
+        flags if flags.is_empty() => "0".to_string(),
+        flags => flags.join("|"),
+    };
+
+    let output = match (syscall, name) {
+        (_, "dfd" | "olddfd" | "newdfd" | "dirfd") if raw as i32 == AT_FDCWD => {
+            "AT_FDCWD".to_string()
+        }
+        ("open" | "openat", "flags") => {
+            let mode = match raw as i32 & libc::O_ACCMODE {
+                libc::O_WRONLY => "O_WRONLY",
+                libc::O_RDWR => "O_RDWR",
+                _ => "O_RDONLY",
+            };
+
+            let mut output = vec![mode.to_string()];
+            output.extend(flags(raw & !(libc::O_ACCMODE as u64), &OPEN_FLAGS));
+            output.join("|")
+        }
+        ("open" | "openat", "mode") => {
+            let flags = reg
+                .args()
+                .into_iter()
+                .find(|(argument, _)| argument_name(argument) == "flags")
+                .map(|(_, flags)| flags as i32)
+                .unwrap_or_default();
+            // `O_TMPFILE` contains the bit of `O_DIRECTORY`
+            if flags & libc::O_CREAT == 0 && flags & libc::O_TMPFILE != libc::O_TMPFILE {
+                return None;
+            }
+            octal(raw)
+        }
+        (_, "mode") if argument.starts_with("umode_t") => octal(raw),
+        ("mmap" | "mprotect", "prot") if raw == 0 => "PROT_NONE".to_string(),
+        ("mmap" | "mprotect", "prot") => flags_of(&PROT_FLAGS),
+        ("mmap", "flags") => flags_of(&MAP_FLAGS),
+        ("access" | "faccessat" | "faccessat2", "mode") if raw == 0 => "F_OK".to_string(),
+        ("access" | "faccessat" | "faccessat2", "mode") => flags_of(&ACCESS_FLAGS),
+        (_, "flag" | "flags") if syscall.ends_with("at") => flags_of(&AT_FLAGS),
+        ("lseek", "whence") => SEEK_WHENCE
+            .get(raw as usize)
+            .map(|whence| whence.to_string())
+            .unwrap_or(raw.to_string()),
+        ("read" | "write" | "pread64" | "pwrite64" | "getrandom", "buf") => {
+            if reg.retval() < 0 {
+                format!("{raw:#x}")
+            } else {
+                quote(&reg.bytes(raw, reg.retval() as usize))
+            }
+        }
+        _ if is_string(argument) => {
+            if raw == 0 {
+                "NULL".to_string()
+            } else {
+                quote(reg.string(raw).as_bytes())
+            }
+        }
+        _ if argument.contains('*')
+            || argument.contains('[')
+            || ["addr", "start", "brk"].contains(&name) =>
+        {
+            if raw == 0 {
+                "NULL".to_string()
+            } else {
+                format!("{raw:#x}")
+            }
+        }
+        _ if argument.starts_with("int ") || argument.starts_with("pid_t ") => {
+            (raw as i32).to_string()
+        }
+        _ if argument.starts_with("long ") || argument.starts_with("off_t ") => {
+            (raw as i64).to_string()
+        }
+        _ => raw.to_string(),
+    };
+
+    Some(output)
+}
+
+/// Returns a line of a syscall, taken at its exit, in the format of strace, like
+/// `openat(AT_FDCWD, "/etc/passwd", O_RDONLY|O_CLOEXEC) = 3 <0.000012>`. The line is prefixed by
+/// `[pid N]` if the syscall has not been made by the traced process `pid`
+pub fn output(reg: &RegistersData, pid: Pid, args: &Args) -> String {
+    let mut output = String::new();
+    if reg.pid() != pid {
+        output.push_str(&format!("[pid {:>5}] ", reg.pid()));
+    }
+
+    if let Some(date) = reg.date(args) {
+        output.push_str(&format!("{date} "));
+    }
+
+    if reg.name().is_empty() {
+        output.push_str(&format!("syscall_{:#x}(", reg.number()));
+    } else {
+        output.push_str(&format!("{}(", reg.name()));
+    }
+
+    let arguments: Vec<String> = reg
+        .args()
+        .into_iter()
+        .filter_map(|(name, raw)| argument(reg, name, raw))
+        .collect();
+    output.push_str(&arguments.join(", "));
+
+    let retval = match reg.errno() {
+        _ if reg.unfinished() => "?".to_string(),
+        Some(errno) => format!("-1 {errno:?} ({})", errno.desc()),
+        None if ADDRESS_RETURNS.contains(&reg.name()) => format!("{:#x}", reg.retval()),
+        None => reg.retval().to_string(),
+    };
+    output.push_str(&format!(") = {retval}"));
+    // A syscall which doesn't return has no duration, like `exit_group(0) = ?`
+    if !reg.unfinished() {
+        output.push_str(&format!(" <{:.6}>", reg.duration().as_secs_f64()));
+    }
+
+    if reg.injected() {
+        output.push_str(" (INJECTED)");
+    }
+    if !reg.tampered_output().is_empty() {
+        output.push_str(&format!(" (TAMPERED {})", reg.tampered_output()));
+    }
+    if let Some(violation) = reg.violation() {
+        output.push_str(&format!(" (VIOLATION: {violation})"));
+    }
+
+    output
+}
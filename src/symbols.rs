@@ -0,0 +1,212 @@
+use gimli::{EndianRcSlice, RunTimeEndian, SectionId};
+use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
+use std::{borrow::Cow, collections::HashMap, fmt, fs, path::Path, rc::Rc};
+
+/// Directory of the separate debug files, eg: installed by the `-dbg` packages
+const DEBUG_DIRECTORY: &str = "/usr/lib/debug";
+
+/// Reader of the DWARF sections of a file
+type Reader = EndianRcSlice<RunTimeEndian>;
+
+/// Returns a Rust or a C++ symbol demangled, or as it is if it isn't mangled
+pub fn demangle(name: &str) -> String {
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        // Without the hash of legacy symbols
+        return format!("{demangled:#}");
+    }
+
+    cpp_demangle::Symbol::new(name)
+        .ok()
+        .and_then(|symbol| symbol.demangle(&Default::default()).ok())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// File of a mapping, shared by every process which maps it. The key of the cache of the
+/// symbolizer, so a file replaced at the same path is loaded again
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MappingKey {
+    pub path: String,
+    pub inode: u64,
+}
+
+/// Function which contains an address, with the offset of the address in it and its source
+/// location when there is debug info
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    /// Demangled name
+    pub name: String,
+    pub offset: u64,
+    /// Source file and line
+    pub location: Option<(String, u32)>,
+}
+
+impl fmt::Display for Symbol {
+    /// Write the symbol like `main+0x14`, without the location
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{:#x}", self.name, self.offset)
+    }
+}
+
+/// Function of the symbol table of an ELF file
+#[derive(Debug)]
+struct Function {
+    address: u64,
+    size: u64,
+    name: String,
+}
+
+/// Symbols and debug info of an ELF file, with the ones of its separate debug file
+struct DebugInfo {
+    /// Functions sorted by address
+    functions: Vec<Function>,
+    lines: Option<addr2line::Context<Reader>>,
+    /// Symbols already resolved by address
+    resolved: HashMap<u64, Option<Symbol>>,
+}
+
+impl DebugInfo {
+    /// Load the ELF file at `path` and its separate debug file, if any. The file is empty if it
+    /// can't be parsed
+    fn load(path: &str) -> Self {
+        let mut info = Self {
+            functions: vec![],
+            lines: None,
+            resolved: HashMap::new(),
+        };
+
+        let Ok(data) = fs::read(path) else {
+            return info;
+        };
+        let Ok(file) = object::File::parse(&*data) else {
+            return info;
+        };
+        info.add_functions(&file);
+
+        // The debug file has the same addresses, with the symbols and the DWARF sections
+        // removed from the file
+        let debug = debug_file(path, &file).and_then(|path| fs::read(path).ok());
+        match debug.as_deref().map(object::File::parse) {
+            Some(Ok(debug)) => {
+                info.add_functions(&debug);
+                info.lines = lines(&debug);
+            }
+            _ => info.lines = lines(&file),
+        }
+
+        info.functions.sort_by_key(|function| function.address);
+        info.functions.dedup_by_key(|function| function.address);
+        info
+    }
+
+    /// Add the functions of the symbol tables of `file`
+    fn add_functions(&mut self, file: &object::File) {
+        self.functions.extend(
+            file.symbols()
+                .chain(file.dynamic_symbols())
+                .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.address() != 0)
+                .filter_map(|symbol| {
+                    Some(Function {
+                        address: symbol.address(),
+                        size: symbol.size(),
+                        name: symbol.name().ok()?.to_string(),
+                    })
+                }),
+        );
+    }
+
+    /// Returns the function which contains `address`, as defined in the file
+    fn symbolize(&mut self, address: u64) -> Option<Symbol> {
+        if let Some(symbol) = self.resolved.get(&address) {
+            return symbol.clone();
+        }
+
+        let function = self
+            .functions
+            .partition_point(|function| function.address <= address)
+            .checked_sub(1)
+            .map(|index| &self.functions[index])
+            .filter(|function| function.size == 0 || address < function.address + function.size);
+
+        let location = self
+            .lines
+            .as_ref()
+            .and_then(|lines| lines.find_location(address).ok().flatten())
+            .and_then(|location| Some((location.file?.to_string(), location.line?)));
+
+        let symbol = function.map(|function| Symbol {
+            name: demangle(&function.name),
+            offset: address - function.address,
+            location,
+        });
+        self.resolved.insert(address, symbol.clone());
+
+        symbol
+    }
+}
+
+/// Returns the path of the separate debug file of the ELF `file` at `path`, found by its
+/// build ID or by its `.gnu_debuglink`
+fn debug_file(path: &str, file: &object::File) -> Option<String> {
+    if let Some(id) = file.build_id().ok().flatten().filter(|id| id.len() > 1) {
+        let hex: String = id[1..].iter().map(|byte| format!("{byte:02x}")).collect();
+        let debug = format!("{DEBUG_DIRECTORY}/.build-id/{:02x}/{hex}.debug", id[0]);
+        if Path::new(&debug).exists() {
+            return Some(debug);
+        }
+    }
+
+    let (name, _) = file.gnu_debuglink().ok().flatten()?;
+    let name = String::from_utf8_lossy(name);
+    let directory = Path::new(path).parent()?.to_string_lossy();
+    [
+        format!("{directory}/{name}"),
+        format!("{directory}/.debug/{name}"),
+        format!("{DEBUG_DIRECTORY}{directory}/{name}"),
+    ]
+    .into_iter()
+    .find(|debug| debug != path && Path::new(debug).exists())
+}
+
+/// Returns the DWARF line info of `file`, if it has some
+fn lines(file: &object::File) -> Option<addr2line::Context<Reader>> {
+    file.section_by_name(".debug_info")?;
+
+    let endian = if file.is_little_endian() {
+        RunTimeEndian::Little
+    } else {
+        RunTimeEndian::Big
+    };
+    let dwarf = gimli::Dwarf::load(|id: SectionId| -> Result<Reader, gimli::Error> {
+        let data = file
+            .section_by_name(id.name())
+            .and_then(|section| section.uncompressed_data().ok())
+            .unwrap_or(Cow::Borrowed(&[]));
+        Ok(EndianRcSlice::new(Rc::from(&*data), endian))
+    })
+    .ok()?;
+
+    addr2line::Context::from_dwarf(dwarf).ok()
+}
+
+/// Resolver of the addresses of mapped files to their functions and source lines, keeping the
+/// files it loaded
+pub struct Symbolizer {
+    files: HashMap<MappingKey, DebugInfo>,
+}
+
+impl Symbolizer {
+    pub fn new() -> Self {
+        Self {
+            files: HashMap::new(),
+        }
+    }
+
+    /// Returns the function which contains `address`, as defined in the ELF file of `mapping`
+    /// and not in the memory of the process
+    pub fn symbolize(&mut self, mapping: &MappingKey, address: u64) -> Option<Symbol> {
+        self.files
+            .entry(mapping.clone())
+            .or_insert_with(|| DebugInfo::load(&mapping.path))
+            .symbolize(address)
+    }
+}
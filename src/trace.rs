@@ -1,22 +1,44 @@
+use crate::chrome;
 use crate::cli::Args;
-use crate::registers::RegistersData;
+use crate::csv;
+use crate::event::OutputFormat;
+use crate::fds::FdTable;
+use crate::filter::is_selected;
+use crate::inject::Injector;
+use crate::output::Output;
+use crate::policy::PolicyAction;
+use crate::record::Recorder;
+use crate::registers::{monotonic, ColorChoice, RegistersData};
+use crate::stack::{Frame, Unwinder};
+use crate::stats::{Latency, Summary};
+use crate::strace;
 use byteorder::{LittleEndian, WriteBytesExt};
+use chrono::{DateTime, Local};
 use nix::{
+    errno::Errno,
+    libc,
     sys::{
-        ptrace,
+        ptrace::{self, Options},
         signal::Signal,
-        wait::{waitpid, WaitStatus},
+        wait::{waitpid, WaitPidFlag, WaitStatus},
     },
     unistd::Pid,
 };
 use std::{
-    fs::File,
-    io::{self, Write},
+    collections::HashMap,
+    io::{self, IsTerminal, Write},
     os::{raw::c_void, unix::process::CommandExt},
     process::{Command, Stdio},
     str,
+    time::Duration,
 };
 
+/// Syscalls which replace the registers and the memory of the process when they succeed
+const EXEC_SYSCALLS: [&str; 2] = ["execve", "execveat"];
+
+/// Syscalls which end the thread, so they never reach their exit
+const EXIT_SYSCALLS: [&str; 2] = ["exit", "exit_group"];
+
 /// Exec the `command` value tracing it with `ptrace` lib
 pub fn exec(command: &str) -> anyhow::Result<()> {
     let params: Vec<&str> = command.split(' ').collect();
@@ -29,9 +51,7 @@ pub fn exec(command: &str) -> anyhow::Result<()> {
         command.pre_exec(|| ptrace::traceme().map_err(|e| e.into()));
     }
 
-    command.exec();
-
-    Ok(())
+    Err(command.exec().into())
 }
 
 /// Attach a ptrace status to a `pid`
@@ -41,59 +61,502 @@ pub fn attach(pid: Pid) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// What happened to a traced process besides its syscalls
+#[derive(Clone, Copy, Debug)]
+pub enum ProcessEventKind {
+    /// The process received a signal, delivered to it when resumed
+    Signal(Signal),
+    /// The process exited with a code
+    Exited(i32),
+    /// The process has been killed by a signal
+    Killed(Signal),
+}
+
+/// Event of a traced process which is not a syscall
+#[derive(Clone, Debug)]
+pub struct ProcessEvent {
+    /// Thread of the event
+    pub pid: Pid,
+    /// Process of the thread, its thread group
+    pub process: Pid,
+    pub timestamp: DateTime<Local>,
+    /// Time of the event from a monotonic clock
+    pub time: Duration,
+    pub kind: ProcessEventKind,
+}
+
+impl ProcessEvent {
+    fn new(pid: Pid, process: Pid, kind: ProcessEventKind) -> Self {
+        Self {
+            pid,
+            process,
+            timestamp: Local::now(),
+            time: monotonic(),
+            kind,
+        }
+    }
+}
+
+/// Source of the syscalls of a process, traced live or loaded from a recording
+pub trait Syscalls {
+    /// Returns the next syscall selected by the filters, taken at its exit. Returns `None` when
+    /// there are no more syscalls
+    fn next(&mut self) -> anyhow::Result<Option<RegistersData>>;
+
+    /// Returns the events of the process since the last call
+    fn process_events(&mut self) -> anyhow::Result<Vec<ProcessEvent>>;
+}
+
+/// State of a traced thread kept between its stops
+#[derive(Default)]
+struct Thread {
+    /// Since you have to do 2 syscalls (start and end) you have to alternate the print value,
+    /// because it could be equals except for the `rax` register.
+    in_syscall: bool,
+    /// Reason why the current syscall is outside the policy, checked at its entry
+    violation: Option<String>,
+    /// Time of the entry of the current syscall, from a monotonic clock
+    entry: Duration,
+    /// Stack of the thread at the entry of the current syscall
+    stack: Vec<Frame>,
+    /// Signal to deliver to the thread when it is resumed
+    signal: Option<Signal>,
+    /// The thread has been created, but the SIGSTOP which starts it hasn't been reported yet
+    starting: bool,
+    /// Leader of the process of the thread, `None` if it is the leader
+    leader: Option<Pid>,
+    /// Current syscall taken at its entry, if it replaces the registers and the memory of the
+    /// process, so its arguments can't be read at its exit
+    exec: Option<RegistersData>,
+}
+
+/// State of the tracing of a process and of its children kept between their stops
+pub struct Tracer<'a> {
+    args: &'a Args,
+    /// Traced threads by ID, of the process and of the children it created since
+    threads: HashMap<Pid, Thread>,
+    /// Thread of the last stop, resumed before waiting for the next one
+    stopped: Option<Pid>,
+    fds: FdTable,
+    injector: Injector,
+    /// Time of the start of the tracing, from a monotonic clock
+    start: Duration,
+    /// Time of the entry of the previous shown syscall, from a monotonic clock
+    previous: Duration,
+    /// Events of the processes since the last call to `process_events`
+    events: Vec<ProcessEvent>,
+    recorder: Option<Recorder>,
+    /// Unwinder of the stack at the entry of every syscall, with `--stack`
+    unwinder: Option<Unwinder>,
+}
+
+impl<'a> Tracer<'a> {
+    /// Trace the process `pid`, stopped, and the children it creates
+    pub fn new(pid: Pid, args: &'a Args) -> anyhow::Result<Self> {
+        ptrace::setoptions(
+            pid,
+            Options::PTRACE_O_TRACESYSGOOD
+                | Options::PTRACE_O_TRACEFORK
+                | Options::PTRACE_O_TRACEVFORK
+                | Options::PTRACE_O_TRACECLONE
+                | Options::PTRACE_O_TRACEEXEC,
+        )?;
+
+        Ok(Self {
+            args,
+            threads: HashMap::from([(pid, Thread::default())]),
+            stopped: Some(pid),
+            fds: FdTable::new(pid),
+            injector: Injector::new(&args.inject, &args.delay),
+            start: monotonic(),
+            previous: monotonic(),
+            events: vec![],
+            recorder: args.record.as_deref().map(Recorder::create).transpose()?,
+            unwinder: args.stack.then(Unwinder::new),
+        })
+    }
+
+    /// Returns the process of the thread `tid`, as the ID of its leader
+    fn process(&self, tid: Pid) -> Pid {
+        self.threads
+            .get(&tid)
+            .and_then(|thread| thread.leader)
+            .unwrap_or(tid)
+    }
+
+    /// Keep an event of the thread `tid`, recording it now so the recording has the events in
+    /// the order they happened
+    fn event(&mut self, tid: Pid, kind: ProcessEventKind) -> anyhow::Result<()> {
+        let event = ProcessEvent::new(tid, self.process(tid), kind);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.process(&event)?;
+        }
+        self.events.push(event);
+
+        Ok(())
+    }
+
+    /// Stop tracing the thread `tid`, which ended
+    fn exit(&mut self, tid: Pid, kind: ProcessEventKind) -> anyhow::Result<()> {
+        self.event(tid, kind)?;
+        self.threads.remove(&tid);
+        self.fds.exit(tid);
+
+        Ok(())
+    }
+
+    /// Returns the registers of the next syscall stop of any thread, keeping the other stops as
+    /// process events. Returns `None` when no thread is traced anymore
+    fn stop(&mut self) -> anyhow::Result<Option<RegistersData>> {
+        loop {
+            if let Some(tid) = self.stopped.take() {
+                let signal = self
+                    .threads
+                    .get_mut(&tid)
+                    .and_then(|thread| thread.signal.take());
+                // The thread could have been killed meanwhile
+                let _ = ptrace::syscall(tid, signal);
+            }
+
+            if self.threads.is_empty() {
+                return Ok(None);
+            }
+
+            // Only the tracees and the children of this thread, so the command of `--pipe-to`,
+            // spawned by another thread, is left to its `Pipe`
+            let status = match waitpid(None, Some(WaitPidFlag::__WALL | WaitPidFlag::__WNOTHREAD)) {
+                Ok(status) => status,
+                Err(Errno::ECHILD) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+            match status {
+                WaitStatus::PtraceSyscall(tid) => {
+                    self.stopped = Some(tid);
+                    let mut reg = RegistersData::new(tid, ptrace::getregs(tid)?);
+                    reg.set_process(self.process(tid));
+                    self.threads.entry(tid).or_default();
+                    return Ok(Some(reg));
+                }
+                // A new child starts stopped by a SIGSTOP which must not be delivered, and it
+                // can be reported before the event of its parent
+                WaitStatus::Stopped(tid, Signal::SIGSTOP)
+                    if self.threads.get(&tid).is_none_or(|thread| thread.starting) =>
+                {
+                    self.threads.entry(tid).or_default().starting = false;
+                    self.stopped = Some(tid);
+                }
+                WaitStatus::Stopped(tid, signal) => {
+                    self.event(tid, ProcessEventKind::Signal(signal))?;
+                    self.threads.entry(tid).or_default().signal = Some(signal);
+                    self.stopped = Some(tid);
+                }
+                // Exits of processes which aren't traced are ignored
+                WaitStatus::Exited(tid, code) if self.threads.contains_key(&tid) => {
+                    self.exit(tid, ProcessEventKind::Exited(code))?;
+                }
+                WaitStatus::Signaled(tid, signal, _) if self.threads.contains_key(&tid) => {
+                    self.exit(tid, ProcessEventKind::Killed(signal))?;
+                }
+                // Creations of children, which are traced with their syscalls
+                WaitStatus::PtraceEvent(tid, _, event)
+                    if [
+                        ptrace::Event::PTRACE_EVENT_FORK,
+                        ptrace::Event::PTRACE_EVENT_VFORK,
+                        ptrace::Event::PTRACE_EVENT_CLONE,
+                    ]
+                    .iter()
+                    .any(|kind| *kind as i32 == event) =>
+                {
+                    if let Ok(child) = ptrace::getevent(tid) {
+                        let child = Pid::from_raw(child as i32);
+                        let flags = clone_flags(tid);
+                        self.fds
+                            .fork(tid, child, flags & libc::CLONE_FILES as u64 != 0);
+                        // A thread is in the process of its creator, with the same leader
+                        let leader =
+                            (flags & libc::CLONE_THREAD as u64 != 0).then(|| self.process(tid));
+                        self.threads
+                            .entry(child)
+                            .or_insert_with(|| Thread {
+                                starting: true,
+                                ..Default::default()
+                            })
+                            .leader = leader;
+                    }
+                    self.stopped = Some(tid);
+                }
+                // An exec by a thread other than the leader takes the ID of the leader, and the
+                // previous ID of the thread disappears without an exit
+                WaitStatus::PtraceEvent(tid, _, event)
+                    if event == ptrace::Event::PTRACE_EVENT_EXEC as i32 =>
+                {
+                    if let Ok(former) = ptrace::getevent(tid) {
+                        let former = Pid::from_raw(former as i32);
+                        if former != tid {
+                            self.threads.remove(&former);
+                            self.fds.exit(former);
+                        }
+                    }
+                    self.stopped = Some(tid);
+                }
+                WaitStatus::PtraceEvent(tid, _, _) => self.stopped = Some(tid),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns the `CLONE_*` flags of the syscall of `tid`, stopped at the event of the creation of
+/// a child, or 0 if it is a `fork` or a `vfork`, which don't take flags
+fn clone_flags(tid: Pid) -> u64 {
+    let Ok(regs) = ptrace::getregs(tid) else {
+        return 0;
+    };
+
+    match regs.orig_rax as i64 {
+        libc::SYS_clone => regs.rdi,
+        // The flags of `clone3` are the first field of the structure it takes
+        libc::SYS_clone3 => ptrace::read(tid, regs.rdi as *mut c_void).unwrap_or_default() as u64,
+        _ => 0,
+    }
+}
+
+impl Syscalls for Tracer<'_> {
+    fn next(&mut self) -> anyhow::Result<Option<RegistersData>> {
+        while let Some(mut reg) = self.stop()? {
+            let thread = self.threads.entry(reg.pid()).or_default();
+            thread.in_syscall ^= true;
+            if thread.in_syscall {
+                if let Some(unwinder) = &mut self.unwinder {
+                    let registers = ptrace::getregs(reg.pid())?;
+                    thread.stack = unwinder.unwind(reg.pid(), &registers);
+                }
+                // After the unwinding, so its time isn't counted in the time of the syscall
+                thread.entry = monotonic();
+                if EXEC_SYSCALLS.contains(&reg.name()) {
+                    reg.capture();
+                    thread.exec = Some(reg.clone());
+                }
+                thread.violation = self
+                    .args
+                    .policy
+                    .as_ref()
+                    .and_then(|policy| policy.check(&reg, &self.fds));
+
+                match (&thread.violation, self.args.policy_action) {
+                    (None, _) | (Some(_), PolicyAction::Log) => self.injector.enter(&reg)?,
+                    (Some(_), PolicyAction::Deny) => self.injector.deny(&reg, Errno::EPERM)?,
+                    (Some(violation), PolicyAction::Kill) => {
+                        reg.set_violation(violation.clone());
+                        reg.set_stack(std::mem::take(&mut thread.stack));
+                        trace_kill(reg.pid())?;
+                        self.stopped = None;
+                        if let Some(recorder) = &mut self.recorder {
+                            recorder.syscall(&reg)?;
+                        }
+                        return Ok(Some(reg));
+                    }
+                }
+
+                // A syscall which ends the thread is taken at its entry, without a result
+                if !EXIT_SYSCALLS.contains(&reg.name()) || self.injector.skipped(reg.pid()) {
+                    continue;
+                }
+                reg.set_unfinished();
+            } else {
+                self.injector.exit(&mut reg)?;
+                if let Some(exec) = thread.exec.take() {
+                    reg.set_arguments(&exec);
+                }
+            }
+            let entry = thread.entry;
+            reg.set_entry(entry);
+            reg.set_stack(std::mem::take(&mut thread.stack));
+            if let Some(violation) = thread.violation.take() {
+                reg.set_violation(violation);
+            }
+
+            if let Some(recorder) = &mut self.recorder {
+                recorder.syscall(&reg)?;
+            }
+
+            let selected = is_selected(self.args, &reg, &self.fds);
+            self.fds.update(&reg);
+            if selected {
+                reg.set_relative(
+                    entry.saturating_sub(self.start),
+                    entry.saturating_sub(self.previous),
+                );
+                self.previous = entry;
+                return Ok(Some(reg));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn process_events(&mut self) -> anyhow::Result<Vec<ProcessEvent>> {
+        Ok(std::mem::take(&mut self.events))
+    }
+}
+
+/// Returns the line of a syscall in the format defined by `args`, with ANSI colors if `color`
+fn line(reg: &mut RegistersData, pid: Pid, args: &Args, color: bool) -> anyhow::Result<String> {
+    let mut line = match args.format {
+        OutputFormat::Text => reg.output(pid, args, color),
+        OutputFormat::Strace => strace::output(reg, pid, args),
+        OutputFormat::Jsonl => serde_json::to_string(&reg.event(args.utc))?,
+        OutputFormat::ChromeTrace => chrome::syscall(reg)?,
+        OutputFormat::Csv => csv::row(reg, args.utc),
+    };
+
+    // The stack is shown beneath the syscall in the formats for humans
+    if matches!(args.format, OutputFormat::Text | OutputFormat::Strace) {
+        for frame in reg.stack() {
+            line.push_str(&format!("\n > {frame}"));
+        }
+    }
+
+    Ok(line)
+}
+
+/// Write a line which is not a syscall, without colors, to the file and to the standard output
+/// as defined by `args`
+fn write_raw(f: &mut Option<Output>, args: &Args, line: &str) -> io::Result<()> {
+    if args.summary_only {
+        return Ok(());
+    }
+
+    if let Some(f) = f {
+        f.write_line(line)?;
+    }
+
+    if args.no_tui {
+        writeln!(io::stdout(), "{line}")?;
+    }
+
+    Ok(())
+}
+
+/// Write the summary or the latency table at the exit to the file and to the standard output.
+/// A table would break the lines of JSON or CSV, so with those formats it is written to the
+/// standard error instead, and only shown by the TUI when it is running
+fn write_table(f: &mut Option<Output>, args: &Args, table: &str) -> io::Result<()> {
+    if !matches!(args.format, OutputFormat::Text | OutputFormat::Strace) {
+        if args.no_tui || args.summary_only {
+            writeln!(io::stderr(), "{table}")?;
+        }
+        return Ok(());
+    }
+
+    if let Some(f) = f {
+        f.write_line(table)?;
+    }
+
+    if args.no_tui || args.summary_only {
+        writeln!(io::stdout(), "{table}")?;
+    }
+
+    Ok(())
+}
+
 /// Trace a process with `pid` ID and returns a list of `RegistersData`
 pub fn trace(pid: Pid, args: &Args) -> anyhow::Result<Vec<RegistersData>> {
     // First wait for the parent process
     _ = waitpid(pid, None)?;
 
+    run(&mut Tracer::new(pid, args)?, pid, args)
+}
+
+/// Write every syscall of `syscalls` made by `pid` and its statistics to the file and to the
+/// standard output as defined by `args`, and returns them
+pub fn run(
+    syscalls: &mut impl Syscalls,
+    pid: Pid,
+    args: &Args,
+) -> anyhow::Result<Vec<RegistersData>> {
     // FIXME: file writing on attachment
-    // If `file_to_print` is not None, create a new file with that value for redirecting all the
-    // output (also in stdout)
-    let mut f = None;
-    if let Some(filename) = &args.file_to_print {
-        f = Some(File::create(filename)?);
-    }
+    // If `file_to_print` or `pipe_to` is not None, redirect all the output to that file or
+    // command (also in stdout)
+    let mut f = Output::open(args)?;
 
     let mut lines: Vec<RegistersData> = Vec::new();
 
-    // Since you have to do 2 syscalls (start and end) you have to alternate the print value,
-    // because it could be equals except for the `rax` register.
-    let mut have_to_print = true;
+    let mut summary = Summary::new(args.summary_sort);
+    let mut latency = Latency::new(args.latency_by_fd);
 
-    let filters: Vec<&str> = match &args.filter {
-        Some(filter) => filter.split(',').collect::<Vec<&str>>(),
-        None => vec![],
-    };
-    while let Some(mut reg) = trace_next(pid)? {
-        have_to_print ^= true;
-        if have_to_print {
-            if !filters.is_empty() && !filters.contains(&reg.name()) {
-                continue;
+    let file_color = args.color == ColorChoice::Always;
+    let stdout_color = args.color.enabled(io::stdout().is_terminal());
+
+    let chrome_trace = args.format == OutputFormat::ChromeTrace;
+    if chrome_trace {
+        write_raw(&mut f, args, chrome::HEADER)?;
+    } else if args.format == OutputFormat::Csv && !args.summary_only {
+        if let Some(ref mut f) = f {
+            f.header(csv::HEADER)?;
+        }
+        if args.no_tui {
+            writeln!(io::stdout(), "{}", csv::HEADER)?;
+        }
+    }
+
+    while let Some(mut reg) = syscalls.next()? {
+        for event in syscalls.process_events()? {
+            if chrome_trace {
+                write_raw(&mut f, args, &chrome::process(&event)?)?;
             }
+        }
+        summary.add(&reg);
+        latency.add(&reg);
 
+        if !args.summary_only {
             if let Some(ref mut f) = f {
-                writeln!(f, "{}", reg.output(pid))?;
+                // The file of every process is written as if it was the traced one
+                let traced = if args.per_process { reg.pid() } else { pid };
+                f.write_syscall(reg.pid(), &line(&mut reg, traced, args, file_color)?)?;
             }
 
             if args.no_tui {
-                writeln!(io::stdout(), "{}", reg.output(pid))?;
+                writeln!(io::stdout(), "{}", line(&mut reg, pid, args, stdout_color)?)?;
             }
+        }
 
-            lines.push(reg);
+        lines.push(reg);
+    }
+    for event in syscalls.process_events()? {
+        if chrome_trace {
+            write_raw(&mut f, args, &chrome::process(&event)?)?;
         }
     }
+    if chrome_trace {
+        let name = match &args.command {
+            Some(command) => command.to_string(),
+            None => format!("{pid}"),
+        };
+        write_raw(&mut f, args, &chrome::footer(pid, &name))?;
+    }
+
+    if args.summary || args.summary_only {
+        write_table(&mut f, args, &summary.to_string())?;
+    }
+
+    if args.latency || args.latency_by_fd {
+        write_table(&mut f, args, &latency.to_string())?;
+    }
+
     Ok(lines)
 }
 
-/// Read memory and returns a string.
+/// Read a NUL-terminated string from the memory of `pid` at `address`, up to `PATH_MAX` bytes.
 /// Thank you https://github.com/JakWai01/lurk/blob/e3a3d6c026bbf818fe1329f8d458be544c3c5ebc/src/arch/mod.rs#L66
-pub fn read_memory(pid: Pid, address: u64) -> String {
-    let mut string = String::new();
+pub fn read_string(pid: Pid, address: u64) -> String {
+    let mut string: Vec<u8> = vec![];
 
     let mut count = 0;
     let word_size = 8;
 
-    loop {
+    'words: while string.len() < 4096 {
         let address = unsafe { (address as *mut c_void).offset(count) };
 
         match ptrace::read(pid, address) {
@@ -103,55 +566,50 @@ pub fn read_memory(pid: Pid, address: u64) -> String {
                     panic!("Failed to write {read} as i64 LittleEndian: {err}");
                 });
 
-                if !bytes
-                    .iter()
-                    .filter(|&b| *b == 0x0)
-                    .collect::<Vec<&u8>>()
-                    .is_empty()
-                {
-                    break;
+                for b in bytes {
+                    if b == 0x0 {
+                        break 'words;
+                    }
+                    string.push(b);
                 }
 
-                bytes.iter().for_each(|b| {
-                    string.push(*b as char);
-                });
-
                 count += word_size;
             }
             Err(_) => break,
         };
     }
 
-    if string.len() > 24 {
-        string = string[..24].to_string();
-        string.push_str("...");
-    }
+    String::from_utf8_lossy(&string).to_string()
+}
 
-    string = string.replace('\n', "\\n");
+/// Read `length` bytes, up to 4096, from the memory of `pid` at `address`
+pub fn read_bytes(pid: Pid, address: u64, length: usize) -> Vec<u8> {
+    let length = length.min(4096);
+    let mut bytes: Vec<u8> = vec![];
 
-    format!("\"{string}\"")
+    while bytes.len() < length {
+        let address = unsafe { (address as *mut c_void).add(bytes.len()) };
+
+        match ptrace::read(pid, address) {
+            Ok(read) => bytes.extend(read.to_le_bytes()),
+            Err(_) => break,
+        }
+    }
+
+    bytes.truncate(length);
+    bytes
 }
 
-/// Get the next step for a ptrace process
-pub fn trace_next(pid: Pid) -> anyhow::Result<Option<RegistersData>> {
-    ptrace::syscall(pid, None)?;
-    let status = waitpid(pid, None).unwrap();
+/// Returns a string read from the memory quoted and truncated for the output
+pub fn quote_string(mut string: String) -> String {
+    if string.chars().count() > 24 {
+        string = string.chars().take(24).collect();
+        string.push_str("...");
+    }
 
-    match status {
-        // Match the stopped value for a process
-        WaitStatus::Stopped(pid, signal) => {
-            match signal {
-                Signal::SIGTRAP => {
-                    let reg = RegistersData::new(ptrace::getregs(pid)?);
-                    return Ok(Some(reg));
-                }
-                _ => {}
-            };
-        }
-        _ => {}
-    };
+    string = string.replace('\n', "\\n");
 
-    Ok(None)
+    format!("\"{string}\"")
 }
 
 /// Kill a process traced by ptrace
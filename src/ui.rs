@@ -1,7 +1,9 @@
 use crate::{
     cli::Args,
     registers::RegistersData,
-    trace::{trace, trace_kill, trace_next},
+    replay::replay,
+    stats::{human, Latency, Summary},
+    trace::{trace, trace_kill, Syscalls, Tracer},
 };
 use crossterm::{
     event::{self, Event, KeyCode},
@@ -12,11 +14,22 @@ use nix::{sys::wait::waitpid, unistd::Pid};
 use ratatui::{prelude::*, widgets::*};
 use std::io::{self, stdout};
 
+/// Height of the pane with the latency histogram
+const HISTOGRAM_HEIGHT: u16 = 12;
+
+/// Height of the pane with the stack of a syscall
+const STACK_HEIGHT: u16 = 12;
+
 pub struct UI {
     height: usize,
     max_lines: usize,
     scroll: usize,
     lines: Vec<RegistersData>,
+    latency: Latency,
+    show_histogram: bool,
+    show_stack: bool,
+    /// Title of the pane with the syscalls: the traced process or the loaded file
+    title: String,
 }
 
 impl UI {
@@ -26,24 +39,29 @@ impl UI {
             max_lines: 0,
             scroll: 0,
             lines: vec![],
+            latency: Latency::new(false),
+            show_histogram: false,
+            show_stack: false,
+            title: String::new(),
         }
     }
 
     pub fn add_line(&mut self, registers: RegistersData) {
+        self.latency.add(&registers);
         self.lines.push(registers);
         self.max_lines = self.lines.len() + 1;
     }
 
-    pub fn get_paragraph(&mut self, pid: Pid) -> Paragraph {
+    pub fn get_paragraph(&mut self, args: &Args) -> Paragraph<'_> {
         let mut lines: Vec<Line> = vec![];
         for line in &mut self.lines {
-            lines.push(line.output_ui(pid));
+            lines.push(line.output_ui(args));
         }
         let paragraph = Paragraph::new(lines)
             .block(
                 Block::default()
                     .border_style(Style::default().fg(Color::Yellow))
-                    .title(format!("[{pid}]"))
+                    .title(self.title.as_str())
                     .title(
                         block::Title::from(format!(
                             "[lines {}-{}]",
@@ -60,60 +78,163 @@ impl UI {
         paragraph
     }
 
-    pub fn start(&mut self, pid: Pid, args: &Args) -> anyhow::Result<()> {
+    /// Returns a bar chart with the latency histogram of the syscall at the top of the screen
+    pub fn get_histogram(&self) -> BarChart<'static> {
+        let name = self
+            .lines
+            .get(self.scroll)
+            .map(|line| line.name().to_string())
+            .unwrap_or_default();
+
+        let bars: Vec<Bar> = self
+            .latency
+            .histogram(&name)
+            .map(|histogram| histogram.powers())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(lower, count)| {
+                Bar::default()
+                    .value(count)
+                    .label(Line::from(format!(">={}", human(lower))))
+            })
+            .collect();
+
+        BarChart::default()
+            .block(
+                Block::default()
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .title(format!("[latency of {name}]"))
+                    .borders(Borders::ALL),
+            )
+            .bar_width(8)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(Color::Blue))
+            .data(BarGroup::default().bars(&bars))
+    }
+
+    /// Returns the stack, taken with `--stack`, of the syscall at the top of the screen
+    pub fn get_stack(&self) -> Paragraph<'static> {
+        let (name, frames) = self
+            .lines
+            .get(self.scroll)
+            .map(|line| {
+                let frames = line
+                    .stack()
+                    .iter()
+                    .map(|frame| Line::from(frame.to_string()))
+                    .collect();
+                (line.name().to_string(), frames)
+            })
+            .unwrap_or_default();
+
+        Paragraph::new::<Vec<Line>>(frames).block(
+            Block::default()
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(format!("[stack of {name}]"))
+                .borders(Borders::ALL),
+        )
+    }
+
+    /// Show the syscalls of the process `pid`, or of the trace loaded with `--load` if `pid` is
+    /// `None`
+    pub fn start(&mut self, pid: Option<Pid>, args: &Args) -> anyhow::Result<()> {
         enable_raw_mode()?;
         stdout().execute(EnterAlternateScreen)?;
         let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
-        let mut have_to_print = true;
-        let mut have_to_trace = args.command.is_none();
         let mut should_quit = false;
 
-        if args.command.is_some() {
-            let registers = trace(pid, args)?;
-            for register in registers {
-                self.add_line(register);
+        // The command is traced before showing the TUI, while an attached process is traced
+        // between the frames
+        let mut tracer = None;
+        match (pid, &args.load) {
+            (Some(pid), None) if args.command.is_some() => {
+                for register in trace(pid, args)? {
+                    self.add_line(register);
+                }
+                self.title = format!("[{pid}]");
+            }
+            (Some(pid), None) => {
+                // First wait for the parent process
+                _ = waitpid(pid, None)?;
+                tracer = Some(Tracer::new(pid, args)?);
+                self.title = format!("[{pid}]");
+            }
+            (_, Some(path)) => {
+                for register in replay(path, args)? {
+                    self.add_line(register);
+                }
+                self.title = format!("[{path}]");
             }
-        } else {
-            // First wait for the parent process
-            _ = waitpid(pid, None)?;
+            (None, None) => {}
         }
 
-        let filters: Vec<&str> = match &args.filter {
-            Some(filter) => filter.split(',').collect::<Vec<&str>>(),
-            None => vec![],
-        };
         while !should_quit {
-            if have_to_trace {
-                if let Some(reg) = trace_next(pid)? {
-                    have_to_print ^= true;
-                    if have_to_print {
-                        if !filters.is_empty() && !filters.contains(&reg.name()) {
-                            continue;
-                        }
-                        self.add_line(reg);
-                    }
-                } else {
-                    have_to_trace = false;
+            if let Some(attached) = &mut tracer {
+                let reg = attached.next()?;
+                attached.process_events()?;
+                match reg {
+                    Some(reg) => self.add_line(reg),
+                    None => tracer = None,
                 }
             }
 
-            self.height = terminal.get_frame().area().height as usize;
+            let histogram = self.show_histogram.then(|| self.get_histogram());
+            let stack = self.show_stack.then(|| self.get_stack());
+            let mut panes = vec![Constraint::Min(0)];
+            if histogram.is_some() {
+                panes.push(Constraint::Length(HISTOGRAM_HEIGHT));
+            }
+            if stack.is_some() {
+                panes.push(Constraint::Length(STACK_HEIGHT));
+            }
+
+            let height = terminal.get_frame().area().height;
+            self.height = panes.iter().fold(height, |height, pane| match pane {
+                Constraint::Length(length) => height.saturating_sub(*length),
+                _ => height,
+            }) as usize;
             terminal.draw(|frame| {
-                let size = frame.area();
+                let layout = Layout::vertical(panes).split(frame.area());
+                frame.render_widget(self.get_paragraph(args), layout[0]);
 
-                frame.render_widget(self.get_paragraph(pid), size);
+                let mut index = 1;
+                if let Some(histogram) = histogram {
+                    frame.render_widget(histogram, layout[index]);
+                    index += 1;
+                }
+                if let Some(stack) = stack {
+                    frame.render_widget(stack, layout[index]);
+                }
             })?;
 
             should_quit = handle_events(self)?;
         }
 
         // FIXME: avoid this kill without Rust errors
-        let _ = trace_kill(pid);
+        if let Some(pid) = pid {
+            let _ = trace_kill(pid);
+        }
 
         disable_raw_mode()?;
         stdout().execute(LeaveAlternateScreen)?;
 
+        if args.summary {
+            let mut summary = Summary::new(args.summary_sort);
+            for line in &self.lines {
+                summary.add(line);
+            }
+            println!("{summary}");
+        }
+
+        if args.latency || args.latency_by_fd {
+            let mut latency = Latency::new(args.latency_by_fd);
+            for line in &self.lines {
+                latency.add(line);
+            }
+            println!("{latency}");
+        }
+
         Ok(())
     }
 }
@@ -126,17 +247,17 @@ fn handle_events(ui: &mut UI) -> io::Result<bool> {
                     KeyCode::Char('q') => {
                         return Ok(true);
                     }
-                    KeyCode::Char('j') | KeyCode::Down => {
-                        if (ui.max_lines >= ui.height) && ui.scroll < (ui.max_lines - ui.height + 1)
-                        {
-                            ui.scroll += 1;
-                        }
+                    KeyCode::Char('j') | KeyCode::Down
+                        if (ui.max_lines >= ui.height)
+                            && ui.scroll < (ui.max_lines - ui.height + 1) =>
+                    {
+                        ui.scroll += 1;
                     }
-                    KeyCode::Char('J') | KeyCode::Char('G') => {
-                        if (ui.max_lines >= ui.height) && ui.scroll < (ui.max_lines - ui.height + 1)
-                        {
-                            ui.scroll = ui.max_lines - ui.height + 1;
-                        }
+                    KeyCode::Char('J') | KeyCode::Char('G')
+                        if (ui.max_lines >= ui.height)
+                            && ui.scroll < (ui.max_lines - ui.height + 1) =>
+                    {
+                        ui.scroll = ui.max_lines - ui.height + 1;
                     }
                     KeyCode::Char('k') | KeyCode::Up => {
                         if ui.scroll > 1 {
@@ -148,6 +269,12 @@ fn handle_events(ui: &mut UI) -> io::Result<bool> {
                     KeyCode::Char('K') | KeyCode::Char('0') => {
                         ui.scroll = 0;
                     }
+                    KeyCode::Char('h') => {
+                        ui.show_histogram ^= true;
+                    }
+                    KeyCode::Char('s') => {
+                        ui.show_stack ^= true;
+                    }
                     _ => {}
                 }
             }